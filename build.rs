@@ -0,0 +1,21 @@
+//! Generates the gRPC server from proto/blurt.proto when the grpc feature is enabled.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// Compile the protobuf definitions without needing protoc installed
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/blurt.proto");
+
+    let descriptors = protox::compile(["proto/blurt.proto"], ["proto"])
+        .expect("failed to parse proto/blurt.proto");
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)
+        .expect("failed to generate the gRPC server");
+}
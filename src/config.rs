@@ -0,0 +1,356 @@
+//! Configuration file holding the same options as the command line.
+//!
+//! Keys are the long flag names, e.g. `max-interval = 60` or
+//! `only = ["com.apple.MobileSMS"]`. Flags passed on the command
+//! line take precedence over the file.
+
+use clap::{ArgMatches, ValueEnum};
+use clap::parser::ValueSource;
+use regex::Regex;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use crate::cli::{expand_tilde, parse_field, Args, ColorChoice, DedupKey, RedisMode, OUTPUT_ARGS};
+use crate::filter::Filter;
+use crate::rate_limit::Rate;
+use crate::schedule::QuietHours;
+use crate::timezone::DisplayTimezone;
+
+/// Options read from the configuration file, unset ones are left alone
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub db_paths: Option<Vec<String>>,
+    pub timezone: Option<String>,
+    pub color: Option<String>,
+    pub interval: Option<u64>,
+    pub max_interval: Option<u64>,
+    pub interval_growth: Option<f64>,
+    pub from_beginning: Option<bool>,
+    pub include_raw: Option<bool>,
+    pub include_metadata: Option<bool>,
+    pub fields: Option<Vec<String>>,
+    pub max_body: Option<u64>,
+    pub delivered_only: Option<bool>,
+    pub watch: Option<bool>,
+    pub watch_updates: Option<bool>,
+    pub only: Option<Vec<String>>,
+    pub ignore: Option<Vec<String>>,
+    pub match_title: Option<Vec<String>>,
+    pub match_body: Option<Vec<String>>,
+    pub filter: Option<String>,
+    pub redact: Option<Vec<String>>,
+    pub hash_body: Option<bool>,
+    pub redact_title: Option<bool>,
+    pub dedup_window: Option<u64>,
+    pub dedup_key: Option<String>,
+    pub quiet: Option<String>,
+    pub quiet_queue: Option<bool>,
+    pub rate: Option<String>,
+    pub wal_mode: Option<bool>,
+    pub snapshot: Option<bool>,
+    pub busy_timeout: Option<u64>,
+    pub busy_retries: Option<u32>,
+    pub max_errors: Option<u32>,
+    pub max_runtime: Option<u64>,
+    pub max_notifications: Option<u64>,
+    pub syslog: Option<bool>,
+    pub syslog_facility: Option<String>,
+    pub log_file: Option<String>,
+    pub log_max_size: Option<u64>,
+    pub notify: Option<bool>,
+    pub telegram_token: Option<String>,
+    pub telegram_chat: Option<String>,
+    pub mqtt_url: Option<String>,
+    pub mqtt_topic: Option<String>,
+    pub ws_addr: Option<String>,
+    pub sse_addr: Option<String>,
+    pub grpc_addr: Option<String>,
+    pub unix_socket: Option<String>,
+    pub smtp_url: Option<String>,
+    pub email_from: Option<String>,
+    pub email_to: Option<Vec<String>>,
+    pub ntfy_url: Option<String>,
+    pub ntfy_token: Option<String>,
+    pub discord_webhook: Option<String>,
+    pub slack_webhook: Option<String>,
+    pub slack_channel: Option<String>,
+    pub nats_url: Option<String>,
+    pub nats_subject: Option<String>,
+    pub redis_url: Option<String>,
+    pub redis_channel: Option<String>,
+    pub redis_mode: Option<String>,
+    pub pushover_token: Option<String>,
+    pub pushover_user: Option<String>,
+    pub pushover_priority: Option<i8>,
+    pub kafka_brokers: Option<String>,
+    pub kafka_topic: Option<String>,
+    pub json_pretty: Option<bool>,
+    pub text: Option<bool>,
+    pub batch: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub dump_failures: Option<String>,
+    pub seen_file: Option<String>,
+    pub seen_capacity: Option<u64>,
+    pub metrics_addr: Option<String>,
+    pub stale_polls: Option<u32>,
+    pub recent: Option<usize>,
+    pub statsd_addr: Option<String>,
+    pub statsd_prefix: Option<String>,
+    pub webhook: Option<String>,
+}
+
+impl Config {
+    /// Read and validate the configuration file at `path`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Can't read config file {}: {}", path.display(), e))?;
+        Self::parse(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Parse and validate the contents of a configuration file
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(contents).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the values the command line would have rejected
+    fn validate(&self) -> Result<(), String> {
+        if self.interval == Some(0) {
+            return Err("interval must be at least 1".to_string());
+        }
+        if self.max_body == Some(0) {
+            return Err("max-body must be at least 1".to_string());
+        }
+        if self.stale_polls == Some(0) {
+            return Err("stale-polls must be at least 1".to_string());
+        }
+        if self.max_errors == Some(0) {
+            return Err("max-errors must be at least 1".to_string());
+        }
+        if self.seen_capacity == Some(0) {
+            return Err("seen-capacity must be at least 1".to_string());
+        }
+        if self.max_runtime == Some(0) {
+            return Err("max-runtime must be at least 1".to_string());
+        }
+        if self.max_notifications == Some(0) {
+            return Err("max-notifications must be at least 1".to_string());
+        }
+        if self.telegram_token.is_some() != self.telegram_chat.is_some() {
+            return Err("telegram-token and telegram-chat must be set together".to_string());
+        }
+        if self.pushover_token.is_some() != self.pushover_user.is_some() {
+            return Err("pushover-token and pushover-user must be set together".to_string());
+        }
+        if self.kafka_brokers.is_some() != self.kafka_topic.is_some() {
+            return Err("kafka-brokers and kafka-topic must be set together".to_string());
+        }
+        if self.pushover_priority.is_some_and(|priority| !(-2..=2).contains(&priority)) {
+            return Err("pushover-priority must be between -2 and 2".to_string());
+        }
+        if self.smtp_url.is_some() && (self.email_from.is_none() || self.email_to.as_ref().is_none_or(Vec::is_empty)) {
+            return Err("smtp-url requires email-from and email-to".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Fill in every option that wasn't passed on the command line
+    ///
+    /// The file's outputs are ignored when one was chosen on the
+    /// command line so it replaces them rather than adding to them.
+    /// Clap only checks which options require or conflict with others
+    /// on the command line, so those involving the file are checked
+    /// here.
+    pub fn apply(self, args: &mut Args, matches: &ArgMatches) -> Result<(), String> {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let cli_output = OUTPUT_ARGS.iter().any(|id| from_cli(id));
+        self.check_requires(&from_cli)?;
+
+        macro_rules! merge {
+            ($field:ident) => {
+                if let Some(value) = self.$field && !from_cli(stringify!($field)) {
+                    args.$field = value;
+                }
+            };
+            ($field:ident => $arg:ident, $convert:expr) => {
+                if let Some(value) = self.$field && !from_cli(stringify!($arg)) {
+                    args.$arg = $convert(value)?;
+                }
+            };
+        }
+        macro_rules! merge_output {
+            ($field:ident) => {
+                if !cli_output {
+                    merge!($field);
+                }
+            };
+            ($field:ident, $convert:expr) => {
+                if !cli_output {
+                    merge!($field => $field, $convert);
+                }
+            };
+        }
+
+        merge!(db_paths => db_paths, expand_paths);
+        merge!(timezone => timezone, |timezone: String| timezone.parse::<DisplayTimezone>()
+            .map(Some)
+            .map_err(|e| format!("Invalid timezone in config: {}", e)));
+        merge!(color => color, |color: String| ColorChoice::from_str(&color, true)
+            .map_err(|_| format!("Invalid color in config: {}, expected auto, always or never", color)));
+        merge!(interval);
+        merge!(max_interval => max_interval, |value| Ok::<_, String>(Some(value)));
+        merge!(interval_growth);
+        merge!(from_beginning);
+        merge!(include_raw);
+        merge!(include_metadata);
+        merge!(fields => fields, |fields: Vec<String>| fields.iter()
+            .map(|field| parse_field(field).map_err(|e| format!("Invalid fields in config: {}", e)))
+            .collect::<Result<Vec<_>, _>>());
+        merge!(max_body => max_body, |value| Ok::<_, String>(Some(value)));
+        merge!(delivered_only);
+        merge!(watch);
+        merge!(watch_updates);
+        merge!(only => allowlist, Ok::<_, String>);
+        merge!(ignore => blocklist, Ok::<_, String>);
+        merge!(match_title => match_title, |patterns| compile_patterns("match-title", patterns));
+        merge!(match_body => match_body, |patterns| compile_patterns("match-body", patterns));
+        merge!(filter => filter, |filter: String| filter.parse::<Filter>()
+            .map(Some)
+            .map_err(|e| format!("Invalid filter in config: {}", e)));
+        merge!(redact => redact, |patterns| compile_patterns("redact", patterns));
+        merge!(hash_body);
+        merge!(redact_title);
+        merge!(dedup_window);
+        merge!(dedup_key => dedup_key, |key: String| DedupKey::from_str(&key, true)
+            .map_err(|_| format!("Invalid dedup-key in config: {}, expected uuid, content or thread", key)));
+        merge!(quiet => quiet, |quiet: String| quiet.parse::<QuietHours>()
+            .map(Some)
+            .map_err(|e| format!("Invalid quiet in config: {}", e)));
+        merge!(quiet_queue);
+        merge!(rate => rate, |rate: String| rate.parse::<Rate>()
+            .map(Some)
+            .map_err(|e| format!("Invalid rate in config: {}", e)));
+        merge!(wal_mode);
+        merge!(snapshot);
+        merge!(busy_timeout);
+        merge!(busy_retries);
+        merge!(max_errors);
+        merge!(max_runtime => max_runtime, |value| Ok::<_, String>(Some(value)));
+        merge!(max_notifications => max_notifications, |value| Ok::<_, String>(Some(value)));
+        merge!(syslog_facility);
+        merge!(log_max_size);
+        merge!(mqtt_topic);
+        merge!(nats_subject);
+        merge!(redis_channel);
+        merge!(redis_mode => redis_mode, |mode: String| RedisMode::from_str(&mode, true)
+            .map_err(|_| format!("Invalid redis-mode in config: {}, expected pub or stream", mode)));
+        merge!(pushover_priority);
+        merge!(json_pretty);
+        merge!(text);
+        merge!(batch);
+        merge!(dry_run);
+        merge!(dump_failures => dump_failures, |path: String| expand_tilde(&path).map(Some));
+        merge!(seen_file => seen_file, |path: String| expand_tilde(&path).map(Some));
+        merge!(seen_capacity);
+        merge!(metrics_addr => metrics_addr, |value| Ok::<_, String>(Some(value)));
+        merge!(stale_polls);
+        merge!(recent);
+        merge!(statsd_addr => statsd_addr, |value| Ok::<_, String>(Some(value)));
+        merge!(statsd_prefix);
+
+        merge_output!(syslog);
+        merge_output!(log_file, |path: String| expand_tilde(&path).map(Some));
+        merge_output!(notify);
+        merge_output!(webhook, |value| Ok::<_, String>(Some(value)));
+        merge_output!(telegram_token, |value| Ok::<_, String>(Some(value)));
+        merge_output!(telegram_chat, |value| Ok::<_, String>(Some(value)));
+        merge_output!(mqtt_url, |value| Ok::<_, String>(Some(value)));
+        merge_output!(ws_addr, |value| Ok::<_, String>(Some(value)));
+        merge_output!(sse_addr, |value| Ok::<_, String>(Some(value)));
+        merge_output!(grpc_addr, |value| Ok::<_, String>(Some(value)));
+        merge_output!(unix_socket, |path: String| expand_tilde(&path).map(Some));
+        merge_output!(smtp_url, |value| Ok::<_, String>(Some(value)));
+        merge_output!(email_from, |value| Ok::<_, String>(Some(value)));
+        merge_output!(email_to);
+        merge_output!(ntfy_url, |value| Ok::<_, String>(Some(value)));
+        merge_output!(ntfy_token, |value| Ok::<_, String>(Some(value)));
+        merge_output!(discord_webhook, |value| Ok::<_, String>(Some(value)));
+        merge_output!(slack_webhook, |value| Ok::<_, String>(Some(value)));
+        merge_output!(slack_channel, |value| Ok::<_, String>(Some(value)));
+        merge_output!(nats_url, |value| Ok::<_, String>(Some(value)));
+        merge_output!(redis_url, |value| Ok::<_, String>(Some(value)));
+        merge_output!(pushover_token, |value| Ok::<_, String>(Some(value)));
+        merge_output!(pushover_user, |value| Ok::<_, String>(Some(value)));
+        merge_output!(kafka_brokers, |value| Ok::<_, String>(Some(value)));
+        merge_output!(kafka_topic, |value| Ok::<_, String>(Some(value)));
+
+        check_conflicts(args)
+    }
+
+    /// Check the options set in the file have the ones they require,
+    /// in the file or on the command line
+    fn check_requires(&self, from_cli: &impl Fn(&str) -> bool) -> Result<(), String> {
+        let requires = [
+            ("interval-growth", self.interval_growth.is_some(), "max_interval", self.max_interval.is_some()),
+            ("quiet-queue", self.quiet_queue == Some(true), "quiet", self.quiet.is_some()),
+            ("syslog-facility", self.syslog_facility.is_some(), "syslog", self.syslog == Some(true)),
+            ("log-max-size", self.log_max_size.is_some(), "log_file", self.log_file.is_some()),
+            ("mqtt-topic", self.mqtt_topic.is_some(), "mqtt_url", self.mqtt_url.is_some()),
+            ("email-from", self.email_from.is_some(), "smtp_url", self.smtp_url.is_some()),
+            ("email-to", self.email_to.is_some(), "smtp_url", self.smtp_url.is_some()),
+            ("ntfy-token", self.ntfy_token.is_some(), "ntfy_url", self.ntfy_url.is_some()),
+            ("slack-channel", self.slack_channel.is_some(), "slack_webhook", self.slack_webhook.is_some()),
+            ("nats-subject", self.nats_subject.is_some(), "nats_url", self.nats_url.is_some()),
+            ("redis-channel", self.redis_channel.is_some(), "redis_url", self.redis_url.is_some()),
+            ("redis-mode", self.redis_mode.is_some(), "redis_url", self.redis_url.is_some()),
+            ("pushover-priority", self.pushover_priority.is_some(), "pushover_token", self.pushover_token.is_some()),
+            ("seen-capacity", self.seen_capacity.is_some(), "seen_file", self.seen_file.is_some()),
+            ("stale-polls", self.stale_polls.is_some(), "metrics_addr", self.metrics_addr.is_some()),
+            ("statsd-prefix", self.statsd_prefix.is_some(), "statsd_addr", self.statsd_addr.is_some()),
+        ];
+        for (key, set, required, in_file) in requires {
+            if set && !in_file && !from_cli(required) {
+                return Err(format!("{} in config requires {}", key, required.replace('_', "-")));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the options merged from the file don't conflict with each other or the command line
+fn check_conflicts(args: &Args) -> Result<(), String> {
+    if args.json_pretty && args.has_output() {
+        return Err("json-pretty only applies to stdout and can't be used with an output".to_string());
+    }
+    if args.text && (args.has_output() || args.json_pretty) {
+        return Err("text only applies to stdout and can't be used with an output or json-pretty".to_string());
+    }
+    if args.from_beginning && (args.once || args.tail.is_some() || args.since_rowid.is_some()) {
+        return Err("from-beginning can't be used with --once, --tail or --since-rowid".to_string());
+    }
+    if (args.max_runtime.is_some() || args.max_notifications.is_some()) && (args.once || args.tail.is_some()) {
+        return Err("max-runtime and max-notifications can't be used with --once or --tail".to_string());
+    }
+    Ok(())
+}
+
+/// Path of the configuration file read when `--config` isn't passed
+pub fn default_config_path() -> Option<PathBuf> {
+    let home = std::env::var("HOME").ok()?;
+    Some(PathBuf::from(home).join(".config/blurt/config.toml"))
+}
+
+/// Expand a leading `~` in each path
+fn expand_paths(paths: Vec<String>) -> Result<Vec<PathBuf>, String> {
+    paths.iter().map(|path| expand_tilde(path)).collect()
+}
+
+/// Compile the patterns of a config key, naming the key when one is invalid
+fn compile_patterns(key: &str, patterns: Vec<String>) -> Result<Vec<Regex>, String> {
+    patterns.iter()
+        .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid {} in config: {}", key, e)))
+        .collect()
+}
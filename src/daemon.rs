@@ -1,30 +1,241 @@
 //! Daemon module for running the notification monitoring daemon.
 
+use std::collections::HashMap;
+use std::sync::Arc;
 use tokio_rusqlite::Connection as TokioConnection;
 use plist::Value;
-use tokio::time::{sleep, Duration};
-use serde_json;
-use crate::database::{NotificationDatabase, Notification};
-
+use regex::Regex;
+use futures::future::join_all;
+use futures::stream::{self, Stream};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tracing::{debug, error, info, trace, warn};
+use crate::database::{self, Action, App, InterruptionLevel, NotificationDatabase, Notification, NotificationEvent, RecordMetadata};
+use crate::dedup::{DedupKey, Deduplicator, DEFAULT_DEDUP_WINDOW};
+use crate::error::BlurtError;
+use crate::filter::Filter;
+use crate::interval::PollInterval;
+use crate::metrics::Metrics;
+use crate::rate_limit::{Rate, RateLimiter};
+use crate::recent::RecentNotifications;
+use crate::schedule::QuietHours;
+use crate::seen::SeenUuids;
+use crate::sink::{ChannelSink, NotificationSink, StdoutSink};
+use crate::watch::DatabaseWatcher;
 #[cfg(feature = "webhook")]
-use reqwest::Client;
+use crate::sink::WebhookSink;
 
-/// The main daemon structure
-pub struct NotificationDaemon {
+
+/// Number of notifications buffered before the poll task waits on the stream consumer
+const STREAM_BUFFER: usize = 64;
+
+/// Default time to wait between checks for new notifications
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default number of times a check is retried when the database is locked
+pub const DEFAULT_BUSY_RETRIES: u32 = 3;
+
+/// Default number of checks in a row that can fail before the daemon gives up
+pub const DEFAULT_MAX_CONSECUTIVE_ERRORS: u32 = 10;
+
+/// Delay before the first retry of a locked check, doubled on each retry
+const BUSY_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A callback invoked with each notification that's dispatched
+pub type NotificationHandler = Box<dyn Fn(&Notification) + Send + Sync>;
+
+/// A callback invoked with the ROWID and raw data of a record that couldn't be parsed
+pub type ParseErrorHandler = Box<dyn Fn(i64, &[u8]) + Send + Sync>;
+
+/// A monitored notification database and how far it's been read
+pub struct Source {
     db: NotificationDatabase,
+    /// Highest ROWID processed, `None` until the first check
     pub last_rowid: Option<i64>,
-    #[cfg(feature = "webhook")]
-    webhook_url: Option<String>,
+    /// Rows of the `app` table, keyed by `app_id`
+    app_cache: HashMap<i64, App>,
+    /// Identity of the database file when it was last checked
+    file_id: Option<u64>,
+    /// Connection kept open between checks
+    conn: Option<TokioConnection>,
+    /// Whether the `record` table was found to have the expected columns
+    schema_checked: bool,
+    /// `request_last_date` and `delivered_date` of each processed record
+    /// when it was last checked, to notice records updated in place
+    record_dates: HashMap<i64, (Option<f64>, Option<f64>)>,
+}
+
+impl Source {
+    fn new(db: NotificationDatabase) -> Self {
+        Self {
+            db,
+            last_rowid: None,
+            app_cache: HashMap::new(),
+            file_id: None,
+            conn: None,
+            schema_checked: false,
+            record_dates: HashMap::new(),
+        }
+    }
+
+    /// Get the database path
+    pub fn db_path(&self) -> &str {
+        self.db.db_path()
+    }
+
+    /// Forget state tied to the old file after the database was replaced
+    ///
+    /// Everything in the replacement is new so it's read from the
+    /// start, unless the first check hasn't happened yet.
+    fn reset(&mut self) {
+        if self.last_rowid.is_some() {
+            self.last_rowid = Some(0);
+        }
+        self.app_cache.clear();
+        self.conn = None;
+        self.schema_checked = false;
+        self.record_dates.clear();
+    }
+}
+
+/// Records of the `record` table to read, by ROWID
+#[derive(Debug, Clone)]
+enum Rows {
+    /// The ones after the first ROWID up to and including the second
+    Range(i64, i64),
+    /// Exactly these, e.g. records updated in place
+    Listed(Vec<i64>),
+}
+
+/// The main daemon structure
+pub struct NotificationDaemon {
+    /// Databases being monitored, each with independent state
+    sources: Vec<Source>,
+    /// How long to wait between checks for new notifications
+    poll_interval: PollInterval,
+    /// Whether the last check found new notifications
+    found_notifications: bool,
+    /// Check as soon as a database changes on disk instead of only polling
+    watch: bool,
+    /// Also emit records whose dates changed since they were processed
+    watch_updates: bool,
+    /// Process notifications already in the database on the first check
+    from_beginning: bool,
+    /// Attach the full plist to each notification
+    include_raw: bool,
+    /// Attach the record's raw column values to each notification
+    include_metadata: bool,
+    /// Only serialize these fields of each notification
+    fields: Option<Arc<[String]>>,
+    /// Truncate bodies longer than this many characters
+    max_body: Option<usize>,
+    /// Skip notifications that were never shown to the user
+    delivered_only: bool,
+    /// Bundle identifiers to dispatch, everything passes when empty
+    allowlist: Vec<String>,
+    /// Bundle identifiers to skip, takes precedence over the allowlist
+    blocklist: Vec<String>,
+    /// Only dispatch notifications whose title matches one of these
+    title_patterns: Vec<Regex>,
+    /// Only dispatch notifications whose body matches one of these
+    body_patterns: Vec<Regex>,
+    /// Only dispatch notifications satisfying this expression
+    filter: Option<Filter>,
+    /// Matches replaced with `***` before dispatch, applied in order
+    redact_patterns: Vec<Regex>,
+    /// Replace bodies with their SHA-256
+    hash_body: bool,
+    /// Redact and hash titles as well as bodies
+    redact_title: bool,
+    /// Drops identical notifications re-posted within a time window
+    dedup: Deduplicator,
+    /// Record UUIDs emitted by this and previous runs
+    seen: Option<SeenUuids>,
+    /// Drops notifications from apps going over their rate
+    rate_limiter: Option<RateLimiter>,
+    /// Local time window in which notifications aren't dispatched
+    quiet_hours: Option<QuietHours>,
+    /// Hold notifications from quiet hours and dispatch them afterwards
+    quiet_queue: bool,
+    /// Notifications held back during quiet hours
+    queued: Vec<Notification>,
+    /// How many times to retry a check when the database is locked
+    busy_retries: u32,
+    /// How many checks in a row can fail before monitoring stops
+    max_consecutive_errors: u32,
+    /// Schema versions known to work, others are warned about
+    tested_schema_versions: Vec<i64>,
+    /// How long monitoring runs before stopping on its own
+    max_runtime: Option<Duration>,
+    /// How many notifications are dispatched before monitoring stops
+    max_notifications: Option<u64>,
+    /// Notifications dispatched by checks so far
+    dispatched: u64,
+    /// Hand the notifications found in a check to the sink together
+    batch: bool,
+    /// Where parsed notifications are dispatched, each receives every notification
+    sinks: Vec<Box<dyn NotificationSink>>,
+    /// Callbacks invoked before each notification is sent to the sink
+    handlers: Vec<NotificationHandler>,
+    /// Callbacks invoked with each record that couldn't be parsed
+    parse_error_handlers: Vec<ParseErrorHandler>,
+    /// Counters and gauges describing the daemon's activity
+    metrics: Arc<Metrics>,
+    /// The last notifications dispatched
+    recent: Arc<RecentNotifications>,
 }
 
 impl NotificationDaemon {
     /// Create a new daemon instance
     pub fn new(db_path: &str) -> Self {
+        Self::from_database(NotificationDatabase::new(db_path))
+    }
+
+    /// Create a new daemon instance reading an already configured database
+    ///
+    /// Used with `NotificationDatabase::new_in_memory` to run the
+    /// daemon without a file on disk.
+    pub fn from_database(db: NotificationDatabase) -> Self {
         Self {
-            db: NotificationDatabase::new(db_path),
-            last_rowid: None,
-            #[cfg(feature = "webhook")]
-            webhook_url: None,
+            sources: vec![Source::new(db)],
+            poll_interval: PollInterval::fixed(DEFAULT_POLL_INTERVAL),
+            found_notifications: false,
+            watch: false,
+            watch_updates: false,
+            from_beginning: false,
+            include_raw: false,
+            include_metadata: false,
+            fields: None,
+            max_body: None,
+            delivered_only: false,
+            allowlist: Vec::new(),
+            blocklist: Vec::new(),
+            title_patterns: Vec::new(),
+            filter: None,
+            body_patterns: Vec::new(),
+            redact_patterns: Vec::new(),
+            hash_body: false,
+            redact_title: false,
+            dedup: Deduplicator::new(DEFAULT_DEDUP_WINDOW),
+            seen: None,
+            rate_limiter: None,
+            quiet_hours: None,
+            quiet_queue: false,
+            queued: Vec::new(),
+            busy_retries: DEFAULT_BUSY_RETRIES,
+            max_consecutive_errors: DEFAULT_MAX_CONSECUTIVE_ERRORS,
+            tested_schema_versions: Vec::new(),
+            max_runtime: None,
+            max_notifications: None,
+            dispatched: 0,
+            batch: false,
+            sinks: vec![Box::new(StdoutSink::default())],
+            handlers: Vec::new(),
+            parse_error_handlers: Vec::new(),
+            metrics: Arc::new(Metrics::default()),
+            recent: Arc::new(RecentNotifications::default()),
         }
     }
 
@@ -32,17 +243,377 @@ impl NotificationDaemon {
     #[cfg(feature = "webhook")]
     pub fn with_webhook(db_path: &str, webhook_url: String) -> Self {
         Self {
-            db: NotificationDatabase::new(db_path),
-            last_rowid: None,
-            webhook_url: Some(webhook_url),
+            sinks: vec![Box::new(WebhookSink::new(webhook_url))],
+            ..Self::new(db_path)
         }
     }
 
+    /// Monitor another database in addition to the existing ones
+    pub fn with_database(mut self, db_path: &str) -> Self {
+        self.sources.push(Source::new(NotificationDatabase::new(db_path)));
+        self
+    }
+
+    /// Get the monitored databases
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    /// Get the highest ROWID processed from the first database
+    pub fn last_rowid(&self) -> Option<i64> {
+        self.sources[0].last_rowid
+    }
+
+    /// Get the metrics updated by the daemon
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Get the history of recently dispatched notifications
+    pub fn recent(&self) -> Arc<RecentNotifications> {
+        self.recent.clone()
+    }
+
+    /// Skip records whose UUID was already seen, saving each one dispatched
+    ///
+    /// The UUIDs are saved after every check that found new records
+    /// so replaying a backlog after a restart doesn't emit the same
+    /// notifications again. Records that were dropped, or are still
+    /// queued for quiet hours, are read again after a restart.
+    pub fn with_seen_uuids(mut self, seen: SeenUuids) -> Self {
+        self.seen = Some(seen);
+        self
+    }
+
+    /// Set how many dispatched notifications are kept in the history
+    pub fn with_recent_capacity(mut self, capacity: usize) -> Self {
+        self.recent = Arc::new(RecentNotifications::new(capacity));
+        self
+    }
+
+    /// Keep the history of dispatched notifications in `recent`
+    ///
+    /// Lets the streaming sinks catch clients up from the same history.
+    pub fn with_recent(mut self, recent: Arc<RecentNotifications>) -> Self {
+        self.recent = recent;
+        self
+    }
+
+    /// Dispatch notifications to the sink instead of stdout
+    pub fn with_sink(self, sink: Box<dyn NotificationSink>) -> Self {
+        self.with_sinks(vec![sink])
+    }
+
+    /// Dispatch every notification to each of the sinks instead of stdout
+    ///
+    /// Sinks are called concurrently and independently, so one that
+    /// fails or is slow doesn't stop the others from receiving the
+    /// notification.
+    pub fn with_sinks(mut self, sinks: Vec<Box<dyn NotificationSink>>) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
+    /// Hand the notifications found in each check to the sink at once
+    ///
+    /// The sink's `handle_batch` is called once per check instead of
+    /// `handle` once per notification, letting sinks like the webhook
+    /// send a single request for a burst.
+    pub fn with_batching(mut self, batch: bool) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Call a closure with each notification that's dispatched
+    ///
+    /// A lightweight alternative to implementing `NotificationSink`.
+    /// Handlers run in the order they were registered, before the
+    /// notification is sent to the sink.
+    pub fn on_notification<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Notification) + Send + Sync + 'static,
+    {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Call a closure with each record whose data couldn't be parsed
+    ///
+    /// Receives the record's ROWID and the raw bytes of its `data`
+    /// column, e.g. to keep the blob around when a new macOS version
+    /// changes the format.
+    pub fn on_parse_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(i64, &[u8]) + Send + Sync + 'static,
+    {
+        self.parse_error_handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Set how long to wait between checks for new notifications
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = PollInterval::fixed(poll_interval);
+        self
+    }
+
+    /// Poll faster after activity and back off towards `max` when idle
+    pub fn with_adaptive_polling(mut self, min: Duration, max: Duration, growth: f64) -> Self {
+        self.poll_interval = PollInterval::adaptive(min, max, growth);
+        self
+    }
+
+    /// Check as soon as a database changes on disk
+    ///
+    /// Polling continues as a fallback so the interval can be set
+    /// much longer. If the files can't be watched the daemon logs a
+    /// warning and relies on polling alone.
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    /// Only serialize these fields of each notification, e.g. to keep payloads small
+    ///
+    /// Names are those in `NOTIFICATION_FIELDS`, unknown ones are
+    /// ignored. An empty list serializes every field.
+    pub fn with_fields(mut self, fields: Vec<String>) -> Self {
+        self.fields = (!fields.is_empty()).then(|| fields.into());
+        self
+    }
+
+    /// Also emit notifications updated in place, tagging each with its event
+    ///
+    /// macOS updates some notifications, like download progress,
+    /// by changing their existing record instead of inserting a new
+    /// one. Every check compares the `request_last_date` and
+    /// `delivered_date` of each record already processed with what
+    /// they were last time, so it reads every row's dates.
+    pub fn with_watch_updates(mut self, watch_updates: bool) -> Self {
+        self.watch_updates = watch_updates;
+        self
+    }
+
+    /// Process every existing notification on the first check
+    ///
+    /// By default the first check only records where the database
+    /// is up to and existing notifications are ignored.
+    pub fn with_from_beginning(mut self, from_beginning: bool) -> Self {
+        self.from_beginning = from_beginning;
+        self
+    }
+
+    /// Process every notification after this ROWID on the first check
+    ///
+    /// Overrides where the first check would otherwise start, to replay
+    /// a known range of records. Applies to every database.
+    pub fn with_start_rowid(mut self, rowid: i64) -> Self {
+        for source in &mut self.sources {
+            source.last_rowid = Some(rowid);
+        }
+        self
+    }
+
+    /// Attach the full plist to each notification for fields blurt doesn't model
+    pub fn with_include_raw(mut self, include_raw: bool) -> Self {
+        self.include_raw = include_raw;
+        self
+    }
+
+    /// Attach the `rec_id`, `app_id`, `presented`, `style` and
+    /// `delivered_date` columns of each notification's record
+    pub fn with_include_metadata(mut self, include_metadata: bool) -> Self {
+        self.include_metadata = include_metadata;
+        self
+    }
+
+    /// Truncate notification bodies to at most `max_body` characters
+    pub fn with_max_body(mut self, max_body: usize) -> Self {
+        self.max_body = Some(max_body);
+        self
+    }
+
+    /// Only dispatch notifications that were presented to the user
+    ///
+    /// Snoozed and scheduled notifications that haven't been shown
+    /// yet are skipped.
+    pub fn with_delivered_only(mut self, delivered_only: bool) -> Self {
+        self.delivered_only = delivered_only;
+        self
+    }
+
+    /// Only dispatch notifications from these bundle identifiers
+    pub fn with_allowlist(mut self, allowlist: Vec<String>) -> Self {
+        self.allowlist = allowlist;
+        self
+    }
+
+    /// Never dispatch notifications from these bundle identifiers
+    pub fn with_blocklist(mut self, blocklist: Vec<String>) -> Self {
+        self.blocklist = blocklist;
+        self
+    }
+
+    /// Only dispatch notifications whose title matches one of these patterns
+    ///
+    /// Title and body patterns are combined so a notification is
+    /// dispatched when any of them match.
+    pub fn with_title_patterns(mut self, title_patterns: Vec<Regex>) -> Self {
+        self.title_patterns = title_patterns;
+        self
+    }
+
+    /// Only dispatch notifications whose body matches one of these patterns
+    pub fn with_body_patterns(mut self, body_patterns: Vec<Regex>) -> Self {
+        self.body_patterns = body_patterns;
+        self
+    }
+
+    /// Only dispatch notifications satisfying a filter expression
+    ///
+    /// Applied on top of the allowlist, blocklist and patterns.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Drop notifications from apps sending more than `rate`
+    ///
+    /// Each bundle identifier is limited separately, notifications
+    /// without one share a limit.
+    pub fn with_rate_limit(mut self, rate: Rate) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(rate));
+        self
+    }
+
+    /// Replace matches of these patterns with `***` before dispatch
+    ///
+    /// Patterns are applied to the body in order, after filtering so
+    /// patterns and deduplication see the original text.
+    pub fn with_redact_patterns(mut self, redact_patterns: Vec<Regex>) -> Self {
+        self.redact_patterns = redact_patterns;
+        self
+    }
+
+    /// Replace each body with the hex SHA-256 of its contents before dispatch
+    pub fn with_hash_body(mut self, hash_body: bool) -> Self {
+        self.hash_body = hash_body;
+        self
+    }
+
+    /// Apply redaction and hashing to titles as well as bodies
+    pub fn with_redact_title(mut self, redact_title: bool) -> Self {
+        self.redact_title = redact_title;
+        self
+    }
+
+    /// Set the window in which identical notifications are dropped, zero disables it
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.dedup = Deduplicator::new(window).with_key(self.dedup.key());
+        self
+    }
+
+    /// Choose what makes notifications identical for deduplication
+    pub fn with_dedup_key(mut self, key: DedupKey) -> Self {
+        self.dedup = self.dedup.with_key(key);
+        self
+    }
+
+    /// Don't dispatch notifications during quiet hours
+    ///
+    /// Notifications are dropped unless `queue` is set, in which
+    /// case they're dispatched once quiet hours end. Either way the
+    /// records are marked as processed.
+    pub fn with_quiet_hours(mut self, quiet_hours: QuietHours, queue: bool) -> Self {
+        self.quiet_hours = Some(quiet_hours);
+        self.quiet_queue = queue;
+        self
+    }
+
+    /// Set how long SQLite waits on a locked database before giving up
+    pub fn with_busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.sources = self.sources.into_iter()
+            .map(|mut source| {
+                source.db = source.db.with_busy_timeout(busy_timeout);
+                source
+            })
+            .collect();
+        self
+    }
+
+    /// Open the database as immutable so the daemon can never write to it
+    pub fn with_immutable(mut self, immutable: bool) -> Self {
+        self.sources = self.sources.into_iter()
+            .map(|mut source| {
+                source.db = source.db.with_immutable(immutable);
+                source
+            })
+            .collect();
+        self
+    }
+
+    /// Read a copy of each database refreshed before every check
+    pub fn with_snapshot(mut self, snapshot: bool) -> Self {
+        self.sources = self.sources.into_iter()
+            .map(|mut source| {
+                source.db = source.db.with_snapshot(snapshot);
+                source
+            })
+            .collect();
+        self
+    }
+
+    /// Set how many times a check is retried when the database is locked
+    pub fn with_busy_retries(mut self, busy_retries: u32) -> Self {
+        self.busy_retries = busy_retries;
+        self
+    }
+
+    /// Set how many checks in a row can fail before monitoring stops
+    ///
+    /// Failed checks are logged and retried at the next poll. The
+    /// count resets whenever a check succeeds.
+    pub fn with_max_consecutive_errors(mut self, max_consecutive_errors: u32) -> Self {
+        self.max_consecutive_errors = max_consecutive_errors;
+        self
+    }
+
+    /// Warn when a database's schema version isn't one of these
+    ///
+    /// The version is `PRAGMA user_version` of the database. Nothing
+    /// is warned about when the list is empty, the default.
+    pub fn with_tested_schema_versions(mut self, versions: Vec<i64>) -> Self {
+        self.tested_schema_versions = versions;
+        self
+    }
+
+    /// Stop monitoring cleanly once it has run for this long
+    pub fn with_max_runtime(mut self, max_runtime: Duration) -> Self {
+        self.max_runtime = Some(max_runtime);
+        self
+    }
+
+    /// Stop monitoring cleanly once this many notifications were dispatched
+    ///
+    /// Notifications found past the limit in the same check are
+    /// dropped so exactly this many are dispatched.
+    pub fn with_max_notifications(mut self, max_notifications: u64) -> Self {
+        self.max_notifications = Some(max_notifications);
+        self
+    }
+
+    /// Get how many notifications checks have dispatched
+    pub fn dispatched_count(&self) -> u64 {
+        self.dispatched
+    }
+
     /// Start the daemon in continuous monitoring mode
-    pub async fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if !self.db.exists() {
-            eprintln!("Database file does not exist: {}", self.db.db_path());
-            return Err("Database file not found".into());
+    pub async fn start(&mut self) -> Result<(), BlurtError> {
+        self.ensure_databases_exist()?;
+
+        // Fail now rather than retrying checks that can never succeed
+        for source in 0..self.sources.len() {
+            let conn = self.sources[source].db.connect().await?;
+            self.ensure_schema(source, &conn).await?;
         }
 
         // Start monitoring loop
@@ -51,17 +622,251 @@ impl NotificationDaemon {
         Ok(())
     }
 
-    /// Monitor notifications continuously
-    async fn monitor_notifications(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Emit every notification currently in the database and return
+    pub async fn run_once(&mut self) -> Result<(), BlurtError> {
+        self.ensure_databases_exist()?;
+
+        for source in 0..self.sources.len() {
+            let conn = self.sources[source].db.connect().await?;
+            self.ensure_schema(source, &conn).await?;
+            let max_rowid = self.query_new_notifications(source, &conn, Rows::Range(0, i64::MAX), NotificationEvent::New).await?;
+            self.sources[source].last_rowid = Some(max_rowid);
+        }
+
+        Ok(())
+    }
+
+    /// Emit the most recent notifications in the database and return
+    ///
+    /// The last `count` records of each database are run through the
+    /// usual filters and dispatched oldest first, so fewer than
+    /// `count` notifications are emitted when some are filtered out.
+    pub async fn run_tail(&mut self, count: u32) -> Result<(), BlurtError> {
+        self.ensure_databases_exist()?;
+
+        for source in 0..self.sources.len() {
+            let conn = self.sources[source].db.connect().await?;
+            self.ensure_schema(source, &conn).await?;
+            let first_rowid = conn.call(move |db_conn| {
+                let first_rowid: Option<i64> = db_conn.query_row(
+                    "SELECT MIN(ROWID) FROM (SELECT ROWID FROM record ORDER BY ROWID DESC LIMIT ?)",
+                    [count],
+                    |row| row.get(0),
+                )?;
+                Ok(first_rowid)
+            }).await?;
+
+            if let Some(first_rowid) = first_rowid {
+                let max_rowid = self.query_new_notifications(source, &conn, Rows::Range(first_rowid - 1, i64::MAX), NotificationEvent::New).await?;
+                self.sources[source].last_rowid = Some(max_rowid);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that every monitored database file exists
+    fn ensure_databases_exist(&self) -> Result<(), BlurtError> {
+        for source in &self.sources {
+            if !source.db.exists() {
+                error!(path = source.db_path(), "Database file does not exist");
+                return Err(BlurtError::DatabaseNotFound(source.db_path().to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consume the daemon and stream each new notification as it's detected
+    ///
+    /// Polling happens in a background task using the same logic as
+    /// `start`. Errors from a check are yielded without ending the
+    /// stream and dropping the stream stops the polling task.
+    pub fn stream(mut self) -> impl Stream<Item = Result<Notification, BlurtError>> {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        self.sinks = vec![Box::new(ChannelSink::new(tx.clone()))];
+
+        tokio::spawn(async move {
+            let mut watcher = self.watch_databases();
+            loop {
+                if let Err(e) = self.check_for_new_notifications().await
+                    && tx.send(Err(e)).await.is_err() {
+                        break;
+                    }
+
+                tokio::select! {
+                    _ = sleep(self.poll_interval.current()) => {}
+                    _ = wait_for_change(&mut watcher) => {}
+                    _ = tx.closed() => break,
+                }
+            }
+            debug!("Notification stream dropped, stopped polling");
+        });
+
+        stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        })
+    }
+
+    /// Monitor notifications continuously until SIGINT or SIGTERM
+    ///
+    /// Signals are only observed between checks so the batch currently
+    /// being processed is always finished before exiting. A failed
+    /// check doesn't stop monitoring unless too many fail in a row.
+    async fn monitor_notifications(&mut self) -> Result<(), BlurtError> {
+        #[cfg(unix)]
+        let mut sigterm = signal(SignalKind::terminate())?;
+        #[cfg(unix)]
+        let mut sigint = signal(SignalKind::interrupt())?;
+        let mut watcher = self.watch_databases();
+        let mut consecutive_errors = 0;
+        let deadline = self.max_runtime.map(|max_runtime| Instant::now() + max_runtime);
+
         loop {
             // Check for new notifications
-            self.check_for_new_notifications().await?;
+            match self.check_for_new_notifications().await {
+                Ok(()) => consecutive_errors = 0,
+                Err(e) => {
+                    consecutive_errors += 1;
+                    if consecutive_errors >= self.max_consecutive_errors {
+                        error!(consecutive_errors, error = %e, "Too many failed checks in a row, giving up");
+                        return Err(e);
+                    }
+                    warn!(consecutive_errors, error = %e, "Check failed, retrying at the next poll");
+                }
+            }
+
+            if self.reached_max_notifications() {
+                info!(dispatched = self.dispatched, "Dispatched the maximum number of notifications, exiting");
+                return Ok(());
+            }
+
+            // Wait before next check
+            #[cfg(unix)]
+            tokio::select! {
+                _ = sleep(self.poll_interval.current()) => {}
+                _ = wait_for_change(&mut watcher) => {}
+                _ = wait_until(deadline) => {
+                    info!("Reached the maximum runtime, exiting");
+                    return Ok(());
+                }
+                _ = sigint.recv() => break,
+                _ = sigterm.recv() => break,
+            }
+            #[cfg(not(unix))]
+            tokio::select! {
+                _ = sleep(self.poll_interval.current()) => {}
+                _ = wait_for_change(&mut watcher) => {}
+                _ = wait_until(deadline) => {
+                    info!("Reached the maximum runtime, exiting");
+                    return Ok(());
+                }
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        info!("Received shutdown signal, exiting cleanly");
+
+        Ok(())
+    }
+
+    /// Start watching the databases if enabled, falling back to polling on failure
+    fn watch_databases(&self) -> Option<DatabaseWatcher> {
+        if !self.watch {
+            return None;
+        }
+
+        match DatabaseWatcher::new(self.sources.iter().map(Source::db_path)) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                warn!(error = %e, "Failed to watch databases, falling back to polling");
+                None
+            }
+        }
+    }
+
+    /// Check every monitored database for new notifications
+    ///
+    /// Databases are checked independently so a failing one doesn't
+    /// stop the others from being checked. The first error, if any,
+    /// is returned after all of them were checked.
+    pub async fn check_for_new_notifications(&mut self) -> Result<(), BlurtError> {
+        self.dedup.evict_stale();
+        if let Some(rate_limiter) = &mut self.rate_limiter {
+            rate_limiter.evict_idle();
+        }
+        let started = Instant::now();
+        self.metrics.record_poll();
+        self.flush_queued().await;
+        self.found_notifications = false;
+
+        let mut first_error = None;
+        for source in 0..self.sources.len() {
+            if let Err(e) = self.check_source(source).await {
+                warn!(path = self.sources[source].db_path(), error = %e, "Failed to check database");
+                first_error.get_or_insert(e);
+            }
+            if let Some(last_rowid) = self.sources[source].last_rowid {
+                self.metrics.record_last_rowid(self.sources[source].db_path(), last_rowid);
+            }
+        }
+
+        self.poll_interval.record_check(self.found_notifications);
+        self.metrics.record_poll_result(first_error.is_none());
+        let latency = started.elapsed();
+        for sink in &self.sinks {
+            sink.record_poll(latency);
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
 
-            // Wait before next check (5 seconds between checks)
-            sleep(Duration::from_secs(5)).await;
+    /// Check a database for new notifications, retrying while it's locked
+    ///
+    /// The OS frequently holds a lock on the live database while
+    /// writing to it. Locked checks are retried with exponential
+    /// backoff up to the configured number of retries.
+    async fn check_source(&mut self, source: usize) -> Result<(), BlurtError> {
+        let mut attempt = 0;
+        loop {
+            match self.check_database(source).await {
+                Err(e) if e.is_busy() && attempt < self.busy_retries => {
+                    let backoff = BUSY_RETRY_BACKOFF * 2u32.pow(attempt);
+                    attempt += 1;
+                    warn!(attempt, ?backoff, "Database is locked, retrying");
+                    sleep(backoff).await;
+                }
+                result => return result,
+            }
         }
     }
 
+    /// Check a database for new notifications, reusing its connection
+    ///
+    /// The connection is kept open between checks to avoid reopening
+    /// the live database on every poll.
+    async fn check_database(&mut self, source: usize) -> Result<(), BlurtError> {
+        self.detect_replacement(source)?;
+
+        let conn = match self.sources[source].conn.take() {
+            Some(conn) => conn,
+            None => self.sources[source].db.connect().await?,
+        };
+        let result = self.check_connection(source, &conn).await;
+
+        // Reuse the connection next time unless it failed, in which
+        // case a fresh one is opened. Immutable and snapshot connections
+        // never see new writes so they can't be reused.
+        if result.is_ok() && self.sources[source].db.reuses_connections() {
+            self.sources[source].conn = Some(conn);
+        }
+
+        result
+    }
+
     /// Check for new notifications since last check
     ///
     /// The max ROWID always goes up but the last ROWID can change
@@ -72,8 +877,8 @@ impl NotificationDaemon {
     /// to the last observed max_id and comparing to the current
     /// max_id. If they don't match, query for everything above the
     /// current max ID.
-    pub async fn check_for_new_notifications(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = self.db.connect().await?;
+    async fn check_connection(&mut self, source: usize, conn: &TokioConnection) -> Result<(), BlurtError> {
+        self.ensure_schema(source, conn).await?;
 
         // Get the maximum ROWID to know how far we've checked
         let max_rowid = conn.call(|db_conn| {
@@ -84,161 +889,701 @@ impl NotificationDaemon {
 
         match max_rowid {
             Some(max_id) => {
-                // If this is our first run, set the initial rowid
-                if self.last_rowid.is_none() {
-                    self.last_rowid = Some(max_id);
-                    return Ok(());
-                }
-
-                let last_rowid = self.last_rowid.unwrap();
+                let last_rowid = match self.sources[source].last_rowid {
+                    Some(last_rowid) => last_rowid,
+                    // On the first run, process the existing backlog if asked to
+                    None if self.from_beginning => 0,
+                    // Otherwise set the initial rowid and skip the backlog,
+                    // still recording the dates updates are noticed by
+                    None => {
+                        self.sources[source].last_rowid = Some(max_id);
+                        max_id
+                    }
+                };
 
-                // If there are new records
+                // Records inserted after reading the max are left for the
+                // next check, the range is pinned so each is read once
                 if max_id > last_rowid {
-                    let new_max_rowid = self.query_new_notifications(&conn, last_rowid).await?;
-                    self.last_rowid = Some(new_max_rowid);
+                    let new_max_rowid = self.query_new_notifications(source, conn, Rows::Range(last_rowid, max_id), NotificationEvent::New).await?;
+                    self.sources[source].last_rowid = Some(new_max_rowid);
                 }
                 // The user dismissed some notices so the ROWID is now lower
                 if max_id < last_rowid {
-                    let new_max_rowid = self.query_new_notifications(&conn, max_id).await?;
-                    self.last_rowid = Some(new_max_rowid);
+                    let new_max_rowid = self.query_new_notifications(source, conn, Rows::Range(max_id, max_id), NotificationEvent::New).await?;
+                    self.sources[source].last_rowid = Some(new_max_rowid);
                 }
+                if max_id == last_rowid {
+                    trace!("No new notifications since last check");
+                }
+
+                if self.watch_updates
+                    && let Some(last_rowid) = self.sources[source].last_rowid {
+                        self.query_updated_notifications(source, conn, last_rowid).await?;
+                    }
             }
             None => {
-                // No records found, nothing to do
+                // There's no backlog to skip, so the baseline is set now
+                // and the first record inserted is emitted
+                trace!("No notifications in the database");
+                self.sources[source].last_rowid.get_or_insert(0);
             }
         }
 
         Ok(())
     }
 
-    /// Query new notifications since last check
-    pub async fn query_new_notifications(&self, conn: &TokioConnection, last_rowid: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    /// Check the database's schema unless it already passed
+    ///
+    /// The schema version is logged when it's first checked, with a
+    /// warning when it's not one blurt was tested against, since a
+    /// macOS update changing the format usually shows up there first.
+    async fn ensure_schema(&mut self, source: usize, conn: &TokioConnection) -> Result<(), BlurtError> {
+        if self.sources[source].schema_checked {
+            return Ok(());
+        }
+
+        let db = &self.sources[source].db;
+        let extra_columns = if self.watch_updates { database::UPDATE_COLUMNS } else { &[] };
+        db.check_schema(conn, extra_columns).await?;
+        let version = db.schema_version(conn).await?;
+        if self.tested_schema_versions.is_empty() || self.tested_schema_versions.contains(&version) {
+            info!(path = db.db_path(), version, "Database schema version");
+        } else {
+            warn!(path = db.db_path(), version, tested = ?self.tested_schema_versions, "Database schema version hasn't been tested, notifications may not be read correctly");
+        }
+        self.metrics.record_schema_version(db.db_path(), version);
+        self.sources[source].schema_checked = true;
+        Ok(())
+    }
+
+    /// Detect the database being deleted or replaced since the last check
+    ///
+    /// macOS occasionally rebuilds the notification database while
+    /// the daemon is running. ROWIDs start over in the new file so
+    /// the source is reset once the file is missing or its inode
+    /// changes.
+    fn detect_replacement(&mut self, source: usize) -> Result<(), BlurtError> {
+        let source = &mut self.sources[source];
+        if !source.db.exists() {
+            source.reset();
+            return Err(BlurtError::DatabaseNotFound(source.db_path().to_string()));
+        }
+
+        let file_id = file_id(source.db_path());
+        if source.file_id.is_some() && file_id != source.file_id {
+            info!(path = source.db_path(), "Database file was replaced, reading it from the start");
+            source.reset();
+        }
+        source.file_id = file_id;
+
+        Ok(())
+    }
+
+    /// Query new notifications in a database since last check
+    ///
+    /// Only the records in `rows` are read. Returns the highest ROWID
+    /// read, or the start of the range when there were none. Records
+    /// updated in place are read again with the `Updated` event, which
+    /// skips the seen UUIDs.
+    async fn query_new_notifications(&mut self, source: usize, conn: &TokioConnection, rows: Rows, event: NotificationEvent) -> Result<i64, BlurtError> {
+        // Columns only read for the metadata aren't required by the schema check
+        let metadata_columns = if self.include_metadata { "rec_id, style" } else { "NULL, NULL" };
+        let (condition, last_rowid) = match rows {
+            Rows::Range(last_rowid, max_rowid) => (format!("ROWID > {} AND ROWID <= {}", last_rowid, max_rowid), last_rowid),
+            // ROWIDs are integers so listing them inline is safe and
+            // isn't bound by SQLite's limit on parameters
+            Rows::Listed(rowids) => {
+                let rowids: Vec<String> = rowids.iter().map(i64::to_string).collect();
+                (format!("ROWID IN ({})", rowids.join(", ")), 0)
+            }
+        };
+        let query = format!(
+            "SELECT ROWID, app_id, delivered_date, uuid, presented, snooze_fire_date, data, {}
+             FROM record WHERE {} ORDER BY ROWID ASC",
+            metadata_columns, condition,
+        );
+
         // Query all new records since last checked ROWID
         let new_records = conn.call(move |db_conn| {
-            let mut stmt = db_conn.prepare("SELECT ROWID, data FROM record WHERE ROWID > ? ORDER BY ROWID ASC")?;
-            let mut rows = stmt.query([last_rowid])?;
+            let mut stmt = db_conn.prepare(&query)?;
+            let mut rows = stmt.query([])?;
 
             let mut records = Vec::new();
             while let Some(row) = rows.next()? {
                 let rowid: i64 = row.get(0)?;
-                let data_bytes: Vec<u8> = row.get(1)?;
-                records.push((rowid, data_bytes));
+                let app_id: Option<i64> = row.get(1)?;
+                let delivered_date: Option<f64> = row.get(2)?;
+                let uuid: Option<Vec<u8>> = row.get(3)?;
+                let presented: Option<bool> = row.get(4)?;
+                let snooze_fire_date: Option<f64> = row.get(5)?;
+                let data_bytes: Option<Vec<u8>> = row.get(6)?;
+                let metadata = RecordMetadata {
+                    rec_id: row.get(7)?,
+                    app_id,
+                    presented,
+                    style: row.get(8)?,
+                    delivered_date,
+                };
+                records.push((rowid, app_id, delivered_date, uuid, presented, snooze_fire_date, metadata, data_bytes.unwrap_or_default()));
             }
 
             Ok(records)
         }).await?;
 
+        if !new_records.is_empty() {
+            match event {
+                NotificationEvent::New => info!(count = new_records.len(), "Found new notifications"),
+                NotificationEvent::Updated => info!(count = new_records.len(), "Found notifications updated in place"),
+            }
+            self.found_notifications = true;
+        }
+
         // Track the actual maximum ROWID we retrieved
         let mut actual_max_rowid = last_rowid;
+        // Notifications held for a single dispatch when batching
+        let mut batch = Vec::new();
 
         // Process each new record
-        for (rowid, bytes) in &new_records {
+        for (rowid, app_id, delivered_date, uuid, presented, snooze_fire_date, metadata, bytes) in &new_records {
             // Update the maximum ROWID seen
             actual_max_rowid = *rowid;
 
-            // Try to parse as binary plist
-            match plist::from_bytes::<Value>(bytes) {
+            // Some records have no payload so there's nothing to parse
+            if bytes.is_empty() {
+                debug!(rowid, "Skipping record without notification data");
+                continue;
+            }
+
+            let uuid = uuid.as_deref().and_then(database::format_uuid);
+            if event == NotificationEvent::New
+                && let Some(seen) = &self.seen
+                && let Some(uuid) = &uuid
+                && seen.contains(uuid) {
+                    debug!(rowid, uuid, "Skipping record that was already seen");
+                    continue;
+                }
+
+            // Try to parse as binary plist, decompressing it if needed
+            match database::read_plist(bytes) {
                 Ok(plist_value) => {
-                    // Parse the plist into our Notification struct
-                    if let Some(notification) = parse_notification_from_plist(&plist_value, *rowid) {
-                        // Either forward the notification to the
-                        // webhook OR print json to stdout
-                        if cfg!(feature = "webhook") {
-                            #[cfg(feature = "webhook")]
-                            if let Some(webhook_url) = &self.webhook_url {
-                                if let Err(e) = forward_to_webhook(webhook_url, &notification).await {
-                                    eprintln!("Failed to forward notification: {}", e);
+                    // Parse the plist into our Notification structs
+                    let notifications = parse_notification_from_plist(&plist_value, *rowid);
+                    if notifications.is_empty() {
+                        warn!(rowid, "Failed to parse notification data into structured format");
+                        self.parse_failed(*rowid, bytes);
+                    }
+
+                    for mut notification in notifications {
+                        if let Some(app_id) = app_id
+                            && let Some(app) = self.resolve_app(source, conn, *app_id).await? {
+                                // Fall back to the app table when the plist has no app field
+                                if notification.bundle_id.is_none() {
+                                    notification.bundle_id = app.identifier;
                                 }
+                                notification.app_name = app.name;
+                            }
+
+                        // The column is more reliable than the plist's date
+                        if let Some(delivered_date) = delivered_date {
+                            notification.date = *delivered_date as i64;
+                        }
+                        notification.uuid = uuid.clone();
+                        notification.set_delivery_state(*presented, *snooze_fire_date);
+
+                        notification.source = Some(self.sources[source].db_path().to_string());
+                        if self.include_raw {
+                            notification.raw = Some(plist_value.clone());
+                        }
+                        if self.include_metadata {
+                            notification.metadata = Some(metadata.clone());
+                        }
+                        if self.watch_updates {
+                            notification.event = Some(event);
+                        }
+                        notification.fields = self.fields.clone();
+                        self.metrics.record_notification(notification.bundle_id.as_deref());
+
+                        if !self.is_allowed(&notification) {
+                            continue;
+                        }
+
+                        if self.delivered_only && !notification.presented {
+                            debug!(id = notification.id, "Dropping notification that wasn't presented");
+                            continue;
+                        }
+
+                        if !self.matches_patterns(&notification) {
+                            debug!(id = notification.id, "Dropping notification not matching any pattern");
+                            continue;
+                        }
+
+                        if let Some(filter) = &self.filter
+                            && !filter.matches(&notification) {
+                                debug!(id = notification.id, "Dropping notification not matching the filter");
+                                continue;
                             }
+
+                        if self.dedup.is_duplicate(&notification) {
+                            debug!(id = notification.id, "Dropping duplicate notification");
+                            continue;
+                        }
+
+                        if let Some(rate_limiter) = &mut self.rate_limiter
+                            && !rate_limiter.allow(notification.bundle_id.as_deref()) {
+                                debug!(id = notification.id, bundle_id = notification.bundle_id, "Dropping notification over the app's rate");
+                                self.metrics.record_rate_limited();
+                                continue;
+                            }
+
+                        notification.redact(&self.redact_patterns, self.redact_title);
+                        if self.hash_body {
+                            notification.hash_body(self.redact_title);
+                        } else if let Some(max_body) = self.max_body {
+                            notification.truncate_body(max_body);
+                        }
+
+                        if self.is_quiet() {
+                            if self.quiet_queue {
+                                debug!(id = notification.id, "Queueing notification during quiet hours");
+                                self.queued.push(notification);
+                            } else {
+                                debug!(id = notification.id, "Dropping notification during quiet hours");
+                            }
+                            continue;
+                        }
+
+                        if self.reached_max_notifications() {
+                            debug!(id = notification.id, "Dropping notification past the maximum");
+                            continue;
+                        }
+                        self.dispatched += 1;
+
+                        self.mark_seen(&notification);
+                        if self.batch {
+                            batch.push(notification);
                         } else {
-                            println!(r"{}", serde_json::to_string(&notification).unwrap());
+                            self.dispatch(&notification).await;
                         }
-                    } else {
-                        eprintln!("Failed to parse notification data into structured format");
                     }
                 }
                 Err(e) => {
-                    eprintln!("Failed to parse as binary plist: {}", e);
+                    warn!(rowid, error = %e, "Failed to parse as binary plist");
+                    self.parse_failed(*rowid, bytes);
                 }
             }
         }
 
+        self.dispatch_batch(&batch).await;
+        self.save_seen();
+
         Ok(actual_max_rowid)
     }
+
+    /// Remember the UUID of a new record's notification once it's dispatched
+    ///
+    /// Records that were dropped or are still queued aren't remembered
+    /// so they're read again after a restart.
+    fn mark_seen(&mut self, notification: &Notification) {
+        if notification.event != Some(NotificationEvent::Updated)
+            && let Some(seen) = &mut self.seen
+            && let Some(uuid) = &notification.uuid {
+                seen.insert(uuid.clone());
+            }
+    }
+
+    /// Save the seen UUIDs, warning when they can't be
+    fn save_seen(&mut self) {
+        if let Some(seen) = &mut self.seen
+            && let Err(e) = seen.save() {
+                warn!(path = %seen.path().display(), error = %e, "Failed to save seen UUIDs");
+            }
+    }
+
+    /// Emit the processed records whose dates changed since the last check
+    ///
+    /// Records seen for the first time only have their dates
+    /// remembered, and records that were deleted are forgotten.
+    async fn query_updated_notifications(&mut self, source: usize, conn: &TokioConnection, last_rowid: i64) -> Result<(), BlurtError> {
+        let dates = conn.call(move |db_conn| {
+            let mut stmt = db_conn.prepare(
+                "SELECT ROWID, request_last_date, delivered_date FROM record WHERE ROWID <= ?"
+            )?;
+            let dates = stmt.query_map([last_rowid], |row| Ok((row.get::<_, i64>(0)?, (row.get(1)?, row.get(2)?))))?
+                .collect::<Result<HashMap<i64, (Option<f64>, Option<f64>)>, _>>()?;
+            Ok(dates)
+        }).await?;
+
+        let previous = std::mem::replace(&mut self.sources[source].record_dates, dates);
+        let updated: Vec<i64> = self.sources[source].record_dates.iter()
+            .filter(|(rowid, dates)| previous.get(rowid).is_some_and(|previous| previous != *dates))
+            .map(|(rowid, _)| *rowid)
+            .collect();
+        if updated.is_empty() {
+            return Ok(());
+        }
+
+        debug!(rowids = ?updated, "Records were updated in place");
+        self.query_new_notifications(source, conn, Rows::Listed(updated), NotificationEvent::Updated).await?;
+        Ok(())
+    }
+
+    /// Count a record that couldn't be parsed and pass it to the handlers
+    fn parse_failed(&self, rowid: i64, data: &[u8]) {
+        self.metrics.record_parse_failure();
+        for handler in &self.parse_error_handlers {
+            handler(rowid, data);
+        }
+    }
+
+    /// Send a notification to the handlers and sinks, logging failures
+    async fn dispatch(&self, notification: &Notification) {
+        for handler in &self.handlers {
+            handler(notification);
+        }
+        self.recent.push(notification);
+
+        let results = join_all(self.sinks.iter().map(|sink| sink.handle(notification))).await;
+        for (sink, result) in self.sinks.iter().zip(results) {
+            if let Err(e) = result {
+                warn!(sink = sink.name(), error = %e, "Failed to dispatch notification");
+            }
+        }
+    }
+
+    /// Send notifications to the handlers and then each sink as one batch
+    async fn dispatch_batch(&self, notifications: &[Notification]) {
+        if notifications.is_empty() {
+            return;
+        }
+
+        for notification in notifications {
+            for handler in &self.handlers {
+                handler(notification);
+            }
+            self.recent.push(notification);
+        }
+
+        let results = join_all(self.sinks.iter().map(|sink| sink.handle_batch(notifications))).await;
+        for (sink, result) in self.sinks.iter().zip(results) {
+            if let Err(e) = result {
+                warn!(sink = sink.name(), count = notifications.len(), error = %e, "Failed to dispatch notifications");
+            }
+        }
+    }
+
+    /// Check if checks dispatched as many notifications as allowed
+    fn reached_max_notifications(&self) -> bool {
+        self.max_notifications.is_some_and(|max_notifications| self.dispatched >= max_notifications)
+    }
+
+    /// Check if quiet hours are currently in effect
+    fn is_quiet(&self) -> bool {
+        self.quiet_hours.is_some_and(|quiet_hours| quiet_hours.is_active())
+    }
+
+    /// Dispatch notifications held back once quiet hours are over
+    async fn flush_queued(&mut self) {
+        if self.queued.is_empty() || self.is_quiet() {
+            return;
+        }
+
+        let mut queued = std::mem::take(&mut self.queued);
+        // Queued notifications count towards the maximum like any other
+        if let Some(max_notifications) = self.max_notifications {
+            let remaining = max_notifications.saturating_sub(self.dispatched) as usize;
+            if queued.len() > remaining {
+                debug!(count = queued.len() - remaining, "Dropping queued notifications past the maximum");
+                queued.truncate(remaining);
+            }
+        }
+        self.dispatched += queued.len() as u64;
+
+        info!(count = queued.len(), "Quiet hours ended, dispatching queued notifications");
+        if self.batch {
+            self.dispatch_batch(&queued).await;
+        } else {
+            for notification in &queued {
+                self.dispatch(notification).await;
+            }
+        }
+        for notification in &queued {
+            self.mark_seen(notification);
+        }
+        self.save_seen();
+    }
+
+    /// Check a notification against the configured blocklist and allowlist
+    fn is_allowed(&self, notification: &Notification) -> bool {
+        if let Some(bundle_id) = &notification.bundle_id
+            && self.blocklist.contains(bundle_id) {
+                debug!(id = notification.id, bundle_id, "Dropping notification from blocked app");
+                return false;
+            }
+
+        if self.allowlist.is_empty() {
+            return true;
+        }
+
+        match &notification.bundle_id {
+            Some(bundle_id) => self.allowlist.contains(bundle_id),
+            None => {
+                debug!(id = notification.id, "Dropping notification without a bundle id because an allowlist is active");
+                false
+            }
+        }
+    }
+
+    /// Check a notification's title and body against the configured patterns
+    fn matches_patterns(&self, notification: &Notification) -> bool {
+        if self.title_patterns.is_empty() && self.body_patterns.is_empty() {
+            return true;
+        }
+
+        self.title_patterns.iter().any(|pattern| pattern.is_match(&notification.title))
+            || self.body_patterns.iter().any(|pattern| pattern.is_match(&notification.body))
+    }
+
+    /// Resolve the `app` table row for an `app_id`, caching the result
+    async fn resolve_app(&mut self, source: usize, conn: &TokioConnection, app_id: i64) -> Result<Option<App>, BlurtError> {
+        let app_cache = &mut self.sources[source].app_cache;
+        if let Some(app) = app_cache.get(&app_id) {
+            return Ok(Some(app.clone()));
+        }
+
+        let app = database::app_info(conn, app_id).await?;
+        if let Some(app) = &app {
+            app_cache.insert(app_id, app.clone());
+        }
+
+        Ok(app)
+    }
+}
+
+/// Identify the file at a path so it being replaced can be detected
+#[cfg(unix)]
+fn file_id(path: &str) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|metadata| metadata.ino())
+}
+
+/// Identify the file at a path so it being replaced can be detected
+#[cfg(not(unix))]
+fn file_id(_path: &str) -> Option<u64> {
+    None
+}
+
+/// Wait until the deadline, or forever when there's none
+async fn wait_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Wait for a watched database to change, or forever when not watching
+async fn wait_for_change(watcher: &mut Option<DatabaseWatcher>) {
+    match watcher {
+        Some(watcher) => watcher.changed().await,
+        None => std::future::pending().await,
+    }
 }
 
-/// Parse a plist Value into a Notification struct
-fn parse_notification_from_plist(plist_value: &Value, rowid: i64) -> Option<Notification> {
-    // Try to extract a dictionary from the plist value
+/// Parse a plist Value into Notification structs
+///
+/// A record usually holds a single dictionary but some hold an array
+/// of them, each of which becomes a notification sharing the record's
+/// ROWID as its `id`. Apps that double-encode their payload store
+/// the plist as data inside another one, which is parsed in turn.
+/// The result is empty when nothing can be parsed.
+pub fn parse_notification_from_plist(plist_value: &Value, rowid: i64) -> Vec<Notification> {
     match plist_value {
-        Value::Dictionary(dict) => {
-            // Extract fields from the main dictionary
-            let mut title = String::new();
-            let mut subtitle: Option<String> = None;
-            let mut body = String::new();
-            let mut date = 0i64;
-            let mut bundle_id: Option<String> = None;
-
-            // Extract bundle ID from the main dictionary (app field)
-            if let Some(bundle_id_value) = dict.get("app")
-                && let Some(bundle_id_str) = bundle_id_value.as_string() {
-                    bundle_id = Some(bundle_id_str.to_string());
-                }
-
-            // Extract date from the main dictionary (date field)
-            if let Some(date_value) = dict.get("date") {
-                // Extract as f64 first, then convert to i64
-                if let Some(date_num) = date_value.as_real() {
-                    date = date_num as i64;
-                }
-            }
-
-            // Look for the nested request dictionary that contains notification details
-            if let Some(req_value) = dict.get("req")
-                && let Value::Dictionary(req_dict) = req_value {
-                    // Extract title from nested req dictionary (field "titl")
-                    if let Some(title_value) = req_dict.get("titl")
-                        && let Some(title_str) = title_value.as_string() {
-                            title = title_str.to_string();
-                        }
+        Value::Dictionary(dict) => vec![parse_notification_dict(dict, rowid)],
+        Value::Array(entries) => entries.iter()
+            .flat_map(|entry| parse_notification_from_plist(entry, rowid))
+            .collect(),
+        Value::Data(bytes) => match database::read_plist(bytes) {
+            Ok(inner) => parse_notification_from_plist(&inner, rowid),
+            Err(e) => {
+                debug!(rowid, error = %e, "Data in the plist isn't a nested plist");
+                Vec::new()
+            }
+        },
+        _ => Vec::new(),
+    }
+}
 
-                    // Extract subtitle from nested req dictionary (field "subt")
-                    if let Some(subtitle_value) = req_dict.get("subt")
-                        && let Some(subtitle_str) = subtitle_value.as_string() {
-                            subtitle = Some(subtitle_str.to_string());
-                        }
+/// Parse a single notification's dictionary
+fn parse_notification_dict(dict: &plist::Dictionary, rowid: i64) -> Notification {
+    // Extract fields from the main dictionary
+    let mut title = String::new();
+    let mut subtitle: Option<String> = None;
+    let mut body = String::new();
+    let mut date = 0i64;
+    let mut bundle_id: Option<String> = None;
+    let mut sound: Option<String> = None;
+    let mut thread_id: Option<String> = None;
+    let mut category: Option<String> = None;
+    let mut interruption_level: Option<InterruptionLevel> = None;
+    let mut attachments: Vec<String> = Vec::new();
+    let mut actions: Vec<Action> = Vec::new();
 
-                    // Extract body from nested req dictionary (field "body")
-                    if let Some(body_value) = req_dict.get("body")
-                        && let Some(body_str) = body_value.as_string() {
-                            body = body_str.to_string();
-                        }
+    // Extract bundle ID from the main dictionary (app field)
+    if let Some(bundle_id_value) = dict.get("app")
+        && let Some(bundle_id_str) = bundle_id_value.as_string() {
+            bundle_id = Some(bundle_id_str.to_string());
+        }
+
+    // Extract date from the main dictionary (date field)
+    if let Some(date_value) = dict.get("date") {
+        // Extract as f64 first, then convert to i64
+        if let Some(date_num) = date_value.as_real() {
+            date = date_num as i64;
+        }
+    }
+
+    // Some payloads keep the thread identifier in the main dictionary
+    if let Some(thread_value) = dict.get("thre")
+        && let Some(thread_str) = thread_value.as_string() {
+            thread_id = Some(thread_str.to_string());
+        }
+
+    // Some payloads keep the category identifier in the main dictionary
+    if let Some(category_value) = dict.get("cate")
+        && let Some(category_str) = category_value.as_string() {
+            category = Some(category_str.to_string());
+        }
+
+    // Look for the nested request dictionary that contains notification details
+    if let Some(req_value) = dict.get("req")
+        && let Value::Dictionary(req_dict) = req_value {
+            // Extract title from nested req dictionary (field "titl")
+            if let Some(title_value) = req_dict.get("titl")
+                && let Some(title_str) = text_value(title_value) {
+                    title = title_str.to_string();
                 }
 
-            // Create and return the Notification struct
-            Some(Notification {
-                id: rowid,
-                title,
-                subtitle,
-                body,
-                date,
-                bundle_id,
-            })
+            // Extract subtitle from nested req dictionary (field "subt")
+            if let Some(subtitle_value) = req_dict.get("subt")
+                && let Some(subtitle_str) = text_value(subtitle_value) {
+                    subtitle = Some(subtitle_str.to_string());
+                }
+
+            // Extract body from nested req dictionary (field "body")
+            if let Some(body_value) = req_dict.get("body")
+                && let Some(body_str) = text_value(body_value) {
+                    body = body_str.to_string();
+                }
+
+            // Extract sound name from nested req dictionary (field "soun")
+            if let Some(sound_value) = req_dict.get("soun")
+                && let Some(sound_str) = sound_value.as_string() {
+                    sound = Some(sound_str.to_string());
+                }
+
+            // Extract thread identifier from nested req dictionary (field "thre")
+            if let Some(thread_value) = req_dict.get("thre")
+                && let Some(thread_str) = thread_value.as_string() {
+                    thread_id = Some(thread_str.to_string());
+                }
+
+            // Extract category identifier from nested req dictionary (field "cate")
+            if let Some(category_value) = req_dict.get("cate")
+                && let Some(category_str) = category_value.as_string() {
+                    category = Some(category_str.to_string());
+                }
+
+            // Extract interruption level from nested req dictionary (field "intl")
+            if let Some(level_value) = req_dict.get("intl") {
+                interruption_level = InterruptionLevel::from_plist(level_value);
+            }
+
+            // Extract attachments from nested req dictionary (field "atta" or "atch")
+            if let Some(Value::Array(attachment_values)) = req_dict.get("atta").or_else(|| req_dict.get("atch")) {
+                attachments = attachment_values.iter()
+                    .filter_map(parse_attachment)
+                    .collect();
+            }
+
+            // Extract action buttons from nested req dictionary (field "acts")
+            if let Some(Value::Array(action_values)) = req_dict.get("acts") {
+                actions = action_values.iter()
+                    .filter_map(parse_action)
+                    .collect();
+            }
         }
-        _ => None
+
+    // Some apps put the subtitle in the main dictionary instead
+    if subtitle.is_none()
+        && let Some(subtitle_value) = dict.get("subt")
+        && let Some(subtitle_str) = text_value(subtitle_value) {
+            subtitle = Some(subtitle_str.to_string());
+        }
+
+    // Create and return the Notification struct
+    Notification {
+        id: rowid,
+        uuid: None,
+        title,
+        subtitle,
+        body,
+        date,
+        presented: true,
+        snoozed: false,
+        bundle_id,
+        app_name: None,
+        sound,
+        thread_id,
+        category,
+        interruption_level,
+        attachments,
+        actions,
+        source: None,
+        raw: None,
+        metadata: None,
+        event: None,
+        fields: None,
     }
 }
 
-/// Forward a notification to a webhook URL via HTTP POST
-#[cfg(feature = "webhook")]
-async fn forward_to_webhook(webhook_url: &str, notification: &Notification) -> Result<(), Box<dyn std::error::Error>> {
-    let client = Client::new();
-    client.post(webhook_url)
-        .timeout(Duration::from_secs(5))
-        .json(notification)
-        .timeout
-        .send()
-        .await?;
-
-    Ok(())
+/// Keys an attributed string keeps its plain text under
+const ATTRIBUTED_TEXT_KEYS: [&str; 4] = ["NSString", "NS.string", "string", "text"];
+
+/// Read the plain text of a string or an attributed string
+///
+/// Attributed strings are a dictionary holding the text alongside
+/// its attributes, sometimes nested another level deep.
+fn text_value(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(text) => Some(text),
+        Value::Dictionary(attributed) => ATTRIBUTED_TEXT_KEYS.iter()
+            .filter_map(|key| attributed.get(key))
+            .find_map(text_value),
+        _ => None,
+    }
+}
+
+/// Parse an action button entry into its title and identifier
+fn parse_action(action_value: &Value) -> Option<Action> {
+    let action_dict = action_value.as_dictionary()?;
+    let title = action_dict.get("titl").and_then(|value| value.as_string())?;
+    let identifier = action_dict.get("iden").and_then(|value| value.as_string())?;
+    Some(Action {
+        title: title.to_string(),
+        identifier: identifier.to_string(),
+    })
+}
+
+/// Parse an attachment entry into its file path or identifier
+///
+/// Entries are either a plain string or a dictionary holding the
+/// file URL ("url") and/or an identifier ("iden").
+fn parse_attachment(attachment_value: &Value) -> Option<String> {
+    match attachment_value {
+        Value::String(path) => Some(path.to_string()),
+        Value::Dictionary(attachment_dict) => attachment_dict.get("url")
+            .or_else(|| attachment_dict.get("iden"))
+            .and_then(|value| value.as_string())
+            .map(|value| value.to_string()),
+        _ => None
+    }
 }
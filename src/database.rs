@@ -1,24 +1,356 @@
 //! Database module for reading macOS notifications from SQLite.
 
 use tokio_rusqlite::Connection as TokioConnection;
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::read::GzDecoder;
+use owo_colors::OwoColorize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
 use std::path::Path;
-use rusqlite::{OpenFlags, params};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use rusqlite::{OpenFlags, OptionalExtension, params, params_from_iter};
+use plist::Value;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+use crate::daemon::parse_notification_from_plist;
+use crate::error::BlurtError;
+use crate::snapshot::Snapshot;
+use crate::timezone::DisplayTimezone;
 
 /// Represents a notification from the system database
-#[derive(Debug, Clone, serde::Serialize)]
+///
+/// Serializing goes through `Serialize for Notification` below so
+/// the derived implementation is only reachable from there.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(remote = "Self")]
 pub struct Notification {
     pub id: i64,
+    /// Identifier from the record's `uuid` column, which unlike the
+    /// ROWID is never reused after deletion
+    pub uuid: Option<String>,
     pub title: String,
     pub subtitle: Option<String>,
     pub body: String,
+    /// Core Data timestamp of delivery, from `delivered_date` when it's set
     pub date: i64,
+    /// Whether the notification was shown to the user
+    pub presented: bool,
+    /// Whether the notification is snoozed to fire again later
+    pub snoozed: bool,
     pub bundle_id: Option<String>,
+    /// Human readable name of the app, when the `app` table has one
+    pub app_name: Option<String>,
+    /// Name of the alert sound, `None` for silent notifications
+    pub sound: Option<String>,
+    /// Identifier grouping related notifications, e.g. a conversation
+    pub thread_id: Option<String>,
+    /// Category the app registered for the notification, selecting its actions and interface
+    pub category: Option<String>,
+    /// How urgently the app asked for the notification to be shown
+    pub interruption_level: Option<InterruptionLevel>,
+    /// File paths or identifiers of attached images and media
+    pub attachments: Vec<String>,
+    /// Buttons offered by interactive notifications
+    pub actions: Vec<Action>,
+    /// Path of the database the notification was read from
+    pub source: Option<String>,
+    /// The full plist the notification was parsed from, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Value>,
+    /// Columns of the notification's `record` row, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RecordMetadata>,
+    /// Whether the record is new or was updated in place, when watching updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<NotificationEvent>,
+    /// Names of the only fields to serialize, every field when `None`
+    #[serde(skip)]
+    pub fields: Option<Arc<[String]>>,
+}
+
+/// Names of the fields a notification serializes, for selecting some of them
+pub const NOTIFICATION_FIELDS: &[&str] = &[
+    "id", "uuid", "title", "subtitle", "body", "date", "presented", "snoozed",
+    "bundle_id", "app_name", "sound", "thread_id", "category", "interruption_level",
+    "attachments", "actions", "source", "raw", "metadata", "event", "delivered_at",
+];
+
+impl serde::Serialize for Notification {
+    /// Serialize every field, or only the selected ones when `fields` is set
+    ///
+    /// Along with the fields, `delivered_at` gives the date as an
+    /// RFC 3339 UTC timestamp, `null` when it's out of range.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let with_date = WithDeliveredAt {
+            notification: self,
+            delivered_at: self.delivered_at().map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        };
+        let Some(fields) = &self.fields else {
+            return with_date.serialize(serializer);
+        };
+
+        let mut value = with_date.serialize(serde_json::value::Serializer)
+            .map_err(serde::ser::Error::custom)?;
+        if let serde_json::Value::Object(object) = &mut value {
+            object.retain(|name, _| fields.iter().any(|field| field == name));
+        }
+        value.serialize(serializer)
+    }
+}
+
+/// A notification's fields followed by its date in RFC 3339
+#[derive(serde::Serialize)]
+struct WithDeliveredAt<'a> {
+    #[serde(flatten, serialize_with = "serialize_fields")]
+    notification: &'a Notification,
+    delivered_at: Option<String>,
+}
+
+fn serialize_fields<S: serde::Serializer>(notification: &&Notification, serializer: S) -> Result<S::Ok, S::Error> {
+    Notification::serialize(notification, serializer)
+}
+
+/// Why a notification was emitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotificationEvent {
+    /// The record was inserted since the last check
+    New,
+    /// An existing record's dates changed, e.g. a progress banner
+    Updated,
+}
+
+impl NotificationEvent {
+    /// Get the name used in JSON output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Updated => "updated",
+        }
+    }
+}
+
+impl Notification {
+    /// When the notification was delivered, `None` if the date is out of range
+    pub fn delivered_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.date.checked_add(CORE_DATA_EPOCH)?, 0)
+    }
+
+    /// One line summary for people, e.g. `[Messages] Alice — On my way`
+    ///
+    /// The app is shown by name when known and by bundle identifier
+    /// otherwise. The dash and body are left out when the body is empty.
+    pub fn summary(&self) -> String {
+        let app = self.app_name.as_deref().or(self.bundle_id.as_deref()).unwrap_or("unknown");
+        if self.body.is_empty() {
+            format!("[{}] {}", app, self.title)
+        } else {
+            format!("[{}] {} — {}", app, self.title, self.body)
+        }
+    }
+
+    /// Describe every field that's set, one `name: value` per line
+    ///
+    /// The date is shown in the timezone, `Display` uses UTC. With
+    /// `color` the title is bold and the app and date are dimmed using
+    /// ANSI escapes, for terminals.
+    pub fn describe(&self, timezone: DisplayTimezone, color: bool) -> String {
+        let bold = |value: &str| if color { value.bold().to_string() } else { value.to_string() };
+        let dimmed = |value: &str| if color { value.dimmed().to_string() } else { value.to_string() };
+
+        let mut lines = vec![format!("title: {}", bold(&self.title))];
+        if let Some(subtitle) = &self.subtitle {
+            lines.push(format!("subtitle: {}", subtitle));
+        }
+        lines.push(format!("body: {}", self.body));
+        let date = match self.delivered_at() {
+            Some(date) => timezone.format(date),
+            None => self.date.to_string(),
+        };
+        lines.push(format!("date: {}", dimmed(&date)));
+        for (name, value) in [("app", &self.app_name), ("bundle_id", &self.bundle_id)] {
+            if let Some(value) = value {
+                lines.push(format!("{}: {}", name, dimmed(value)));
+            }
+        }
+        let optional = [
+            ("sound", &self.sound),
+            ("thread_id", &self.thread_id),
+            ("category", &self.category),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                lines.push(format!("{}: {}", name, value));
+            }
+        }
+        if let Some(level) = self.interruption_level {
+            lines.push(format!("interruption_level: {}", level.as_str()));
+        }
+        for attachment in &self.attachments {
+            lines.push(format!("attachment: {}", attachment));
+        }
+        for action in &self.actions {
+            lines.push(format!("action: {} ({})", action.title, action.identifier));
+        }
+        lines.join("\n")
+    }
+
+    /// Shorten the body to at most `max_chars` characters
+    ///
+    /// Truncated bodies end with an ellipsis, which counts towards
+    /// the limit. Characters are never split.
+    pub fn truncate_body(&mut self, max_chars: usize) {
+        if self.body.chars().count() > max_chars {
+            self.body = truncate(&self.body, max_chars);
+        }
+    }
+
+    /// Replace every match of the patterns in the body with `***`
+    ///
+    /// Patterns are applied in order, each to the result of the
+    /// previous one. The title is redacted too with `include_title`.
+    pub fn redact(&mut self, patterns: &[Regex], include_title: bool) {
+        for pattern in patterns {
+            self.body = pattern.replace_all(&self.body, REDACTED).into_owned();
+            if include_title {
+                self.title = pattern.replace_all(&self.title, REDACTED).into_owned();
+            }
+        }
+    }
+
+    /// Replace the body with the hex SHA-256 of its contents
+    ///
+    /// The title is hashed too with `include_title`.
+    pub fn hash_body(&mut self, include_title: bool) {
+        self.body = hex::encode(Sha256::digest(self.body.as_bytes()));
+        if include_title {
+            self.title = hex::encode(Sha256::digest(self.title.as_bytes()));
+        }
+    }
+
+    /// Set `presented` and `snoozed` from the record's columns
+    ///
+    /// Records without a `presented` value are assumed to have been
+    /// shown and a snooze fire date of zero means no snooze.
+    pub(crate) fn set_delivery_state(&mut self, presented: Option<bool>, snooze_fire_date: Option<f64>) {
+        self.presented = presented.unwrap_or(true);
+        self.snoozed = snooze_fire_date.is_some_and(|date| date > 0.0);
+    }
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.describe(DisplayTimezone::Utc, false))
+    }
+}
+
+/// Seconds between the Unix epoch and the Core Data epoch of 2001-01-01 UTC
+///
+/// Add it to a notification's `date` to get a Unix timestamp.
+pub const CORE_DATA_EPOCH: i64 = 978_307_200;
+
+/// Bytes every gzip stream starts with
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Text that redacted matches are replaced with
+const REDACTED: &str = "***";
+
+/// Columns of the `record` table the daemon reads, besides its ROWID
+pub const RECORD_COLUMNS: &[&str] = &["app_id", "uuid", "data", "delivered_date", "presented", "snooze_fire_date"];
+
+/// Columns of the `record` table only read when watching for updates
+pub const UPDATE_COLUMNS: &[&str] = &["request_last_date"];
+
+/// Default time SQLite waits on a locked database before returning SQLITE_BUSY
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Raw column values of a notification's row in the `record` table
+///
+/// Kept as stored for forensic use, e.g. `delivered_date` is the
+/// Core Data timestamp with its fractional seconds.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct RecordMetadata {
+    pub rec_id: Option<i64>,
+    pub app_id: Option<i64>,
+    pub presented: Option<bool>,
+    pub style: Option<i64>,
+    pub delivered_date: Option<f64>,
+}
+
+/// An action button on an interactive notification
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Action {
+    pub title: String,
+    pub identifier: String,
+}
+
+/// How a notification may interrupt the user, set by the app
+///
+/// Mirrors `UNNotificationInterruptionLevel`, from least to most
+/// urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InterruptionLevel {
+    /// Added to the list without lighting up the screen or playing a sound
+    Passive,
+    /// Shown immediately, the default
+    Active,
+    /// Shown immediately even during Focus
+    TimeSensitive,
+    /// Shown and played even when muted or during Focus
+    Critical,
+}
+
+impl InterruptionLevel {
+    /// Name of the level as it's serialized, e.g. `time-sensitive`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Passive => "passive",
+            Self::Active => "active",
+            Self::TimeSensitive => "time-sensitive",
+            Self::Critical => "critical",
+        }
+    }
+
+    /// Read the level from its plist value, `None` when it's not one we know
+    ///
+    /// Stored as the raw value of `UNNotificationInterruptionLevel`
+    /// (0 to 3) or, by some versions, as its name.
+    pub fn from_plist(value: &Value) -> Option<Self> {
+        if let Some(level) = value.as_signed_integer() {
+            return match level {
+                0 => Some(Self::Passive),
+                1 => Some(Self::Active),
+                2 => Some(Self::TimeSensitive),
+                3 => Some(Self::Critical),
+                _ => None,
+            };
+        }
+
+        match value.as_string()?.to_ascii_lowercase().as_str() {
+            "passive" => Some(Self::Passive),
+            "active" => Some(Self::Active),
+            "timesensitive" | "time-sensitive" | "time_sensitive" => Some(Self::TimeSensitive),
+            "critical" => Some(Self::Critical),
+            _ => None,
+        }
+    }
 }
 
 /// Database handler for macOS notification database
 pub struct NotificationDatabase {
     db_path: String,
     read_only: bool,
+    immutable: bool,
+    busy_timeout: Duration,
+    /// Keeps an in-memory database alive, it's freed with its last connection
+    memory: Option<Mutex<rusqlite::Connection>>,
+    /// Copy read instead of the database itself
+    snapshot: Option<Snapshot>,
 }
 
 impl NotificationDatabase {
@@ -27,33 +359,185 @@ impl NotificationDatabase {
         Self {
             db_path: db_path.to_string(),
             read_only: true,
+            immutable: false,
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            memory: None,
+            snapshot: None,
         }
     }
 
     /// Create a new database handler with specified read-only mode
+    ///
+    /// Pass `read_only: false` to build fixtures for tests or
+    /// integrations: call `init_schema` and insert records with
+    /// `insert_test_notification` to get a database the daemon can read.
     pub fn new_with_mode(db_path: &str, read_only: bool) -> Self {
         Self {
             db_path: db_path.to_string(),
             read_only,
+            immutable: false,
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            memory: None,
+            snapshot: None,
         }
     }
 
-    /// Connect to the database
-    pub async fn connect(&self) -> Result<TokioConnection, Box<dyn std::error::Error>> {
-        let db_path = self.db_path.clone();
-        let flags = if self.read_only {
-            OpenFlags::SQLITE_OPEN_READ_ONLY
-        } else {
-            OpenFlags::default()
+    /// Create a writable database that only exists in memory, with the schema in place
+    ///
+    /// Every connection shares the same database through a shared
+    /// cache URI, so the daemon can read what's inserted with
+    /// `insert_test_notification`. It's gone once the handler is
+    /// dropped. Meant for tests and embedding without a file.
+    pub async fn new_in_memory() -> Result<Self, BlurtError> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let name = format!("blurt-{}-{}", std::process::id(), NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let uri = format!("file:{}?mode=memory&cache=shared", name);
+
+        let keeper = rusqlite::Connection::open_with_flags(&uri, OpenFlags::default())?;
+        let db = Self {
+            memory: Some(Mutex::new(keeper)),
+            ..Self::new_with_mode(&uri, false)
         };
-        let conn = tokio_rusqlite::Connection::open_with_flags(db_path, flags).await?;
+        db.init_schema().await?;
+        Ok(db)
+    }
+
+    /// Set how long SQLite waits on a locked database before giving up
+    pub fn with_busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+
+    /// Open read-only connections as immutable
+    ///
+    /// SQLite then skips locking and never writes to the database or
+    /// its WAL, which is what we want for the live macOS database.
+    /// Rows still in the WAL aren't seen until they're checkpointed
+    /// into the main file. Only applies to read-only handlers.
+    pub fn with_immutable(mut self, immutable: bool) -> Self {
+        self.immutable = immutable;
+        self
+    }
+
+    /// Read a copy of the database, refreshed on every connection
+    ///
+    /// Connections never touch the live database or its locks, but
+    /// each one copies it first. Only applies to read-only handlers,
+    /// the copy is removed when the handler is dropped.
+    pub fn with_snapshot(mut self, snapshot: bool) -> Self {
+        self.snapshot = snapshot.then(|| Snapshot::new(&self.db_path));
+        self
+    }
+
+    /// Get the path of the copy read in snapshot mode
+    pub fn snapshot_path(&self) -> Option<&Path> {
+        self.snapshot.as_ref().map(Snapshot::path)
+    }
+
+    /// Connect to the database
+    pub async fn connect(&self) -> Result<TokioConnection, BlurtError> {
+        if self.read_only {
+            return self.connect_readonly().await;
+        }
+
+        self.open(self.db_path.clone(), OpenFlags::default()).await
+    }
+
+    /// Connect to the database without allowing writes
+    pub async fn connect_readonly(&self) -> Result<TokioConnection, BlurtError> {
+        // The copy is private so it's opened normally to read its write-ahead log
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.refresh().await?;
+            let path = snapshot.path().to_string_lossy().into_owned();
+            return self.open(path, OpenFlags::SQLITE_OPEN_READ_ONLY).await;
+        }
+
+        if self.immutable {
+            let uri = format!("file:{}?immutable=1", escape_uri_path(&self.db_path));
+            let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI;
+            return self.open(uri, flags).await;
+        }
+
+        self.open(self.db_path.clone(), OpenFlags::SQLITE_OPEN_READ_ONLY).await
+    }
+
+    /// Open a connection with the given flags and configure it
+    async fn open(&self, path: String, flags: OpenFlags) -> Result<TokioConnection, BlurtError> {
+        let conn = tokio_rusqlite::Connection::open_with_flags(path, flags).await?;
+
+        // Wait for the OS to finish writing instead of failing immediately
+        let busy_timeout_ms = self.busy_timeout.as_millis() as i64;
+        conn.call(move |db_conn| {
+            db_conn.pragma_update(None, "busy_timeout", busy_timeout_ms)?;
+            Ok(())
+        }).await?;
+
         Ok(conn)
     }
 
-    
+    /// Check if connections are opened as immutable
+    ///
+    /// Immutable connections never see changes made after they were
+    /// opened, so a new one is needed to read new notifications.
+    pub fn is_immutable(&self) -> bool {
+        self.read_only && self.immutable
+    }
+
+    /// Check if a connection can be reused to read new notifications
+    ///
+    /// Not when it's immutable or reading a snapshot, which is only
+    /// refreshed when connecting.
+    pub fn reuses_connections(&self) -> bool {
+        !self.read_only || (!self.immutable && self.snapshot.is_none())
+    }
+
+    /// Check the `record` table has every column the daemon reads
+    ///
+    /// Fails with `IncompatibleDatabase` when there's no `record`
+    /// table at all, e.g. when pointed at the wrong file, and with
+    /// `IncompatibleSchema` naming the first missing column so a change
+    /// in a new macOS version is reported clearly instead of as an SQL
+    /// error. Columns in `extra_columns` are required too, for the ones
+    /// only read by some options.
+    pub async fn check_schema(&self, conn: &TokioConnection, extra_columns: &[&str]) -> Result<(), BlurtError> {
+        let (has_record, columns) = conn.call(|db_conn| {
+            let has_record: bool = db_conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'record')",
+                [],
+                |row| row.get(0),
+            )?;
+            let mut stmt = db_conn.prepare("SELECT name FROM pragma_table_info('record')")?;
+            let columns = stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((has_record, columns))
+        }).await?;
+
+        if !has_record {
+            return Err(BlurtError::IncompatibleDatabase(self.db_path.clone()));
+        }
+
+        match RECORD_COLUMNS.iter().chain(extra_columns).find(|column| !columns.iter().any(|c| c.eq_ignore_ascii_case(column))) {
+            Some(column) => Err(BlurtError::IncompatibleSchema {
+                path: self.db_path.clone(),
+                column: column.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Read the schema version the database records in `PRAGMA user_version`
+    ///
+    /// Zero when the database never set one.
+    pub async fn schema_version(&self, conn: &TokioConnection) -> Result<i64, BlurtError> {
+        let version = conn.call(|db_conn| {
+            Ok(db_conn.pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))?)
+        }).await?;
+        Ok(version)
+    }
+
     /// Check if the database file exists
     pub fn exists(&self) -> bool {
-        Path::new(&self.db_path).exists()
+        self.memory.is_some() || Path::new(&self.db_path).exists()
     }
 
     /// Get the database path
@@ -61,11 +545,79 @@ impl NotificationDatabase {
         &self.db_path
     }
 
+    /// Get notifications delivered after a Core Data timestamp
+    ///
+    /// Core Data timestamps are seconds since 2001-01-01 UTC, which is
+    /// how the `delivered_date` column is stored. Records that can't
+    /// be parsed are skipped.
+    pub async fn notifications_since(&self, core_data_ts: i64) -> Result<Vec<Notification>, BlurtError> {
+        self.query_notifications("WHERE delivered_date > ? ORDER BY delivered_date ASC", vec![core_data_ts]).await
+    }
+
+    /// Get every notification in the database, oldest first
+    ///
+    /// Records that can't be parsed are skipped.
+    pub async fn all_notifications(&self) -> Result<Vec<Notification>, BlurtError> {
+        self.query_notifications("ORDER BY ROWID ASC", Vec::new()).await
+    }
+
+    /// Get the notifications of the records selected by `clause`, which follows `FROM record`
+    ///
+    /// Apps are looked up in the `app` table the way the daemon does,
+    /// for the app's name and the identifier of plists without one.
+    async fn query_notifications(&self, clause: &'static str, params: Vec<i64>) -> Result<Vec<Notification>, BlurtError> {
+        let conn = self.connect().await?;
+        let (apps, records) = conn.call(move |db_conn| {
+            let mut stmt = db_conn.prepare("SELECT * FROM app")?;
+            let apps = stmt.query_map([], |row| Ok((row.get("app_id")?, app_from_row(row)?)))?
+                .collect::<Result<HashMap<i64, App>, _>>()?;
+
+            let mut stmt = db_conn.prepare(&format!(
+                "SELECT ROWID, app_id, delivered_date, uuid, presented, snooze_fire_date, data
+                 FROM record {}",
+                clause,
+            ))?;
+            let records = stmt.query_map(params_from_iter(params), |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                    row.get::<_, Option<Vec<u8>>>(3)?,
+                    row.get::<_, Option<bool>>(4)?,
+                    row.get::<_, Option<f64>>(5)?,
+                    row.get::<_, Option<Vec<u8>>>(6)?.unwrap_or_default(),
+                ))
+            })?.collect::<Result<Vec<_>, _>>()?;
+            Ok((apps, records))
+        }).await?;
+
+        Ok(parse_records(records, &apps))
+    }
+
+    /// Get every row of the `app` table as `(app_id, app)`
+    ///
+    /// Apps are sorted by bundle identifier, ones without an
+    /// identifier last.
+    pub async fn list_apps(&self) -> Result<Vec<(i64, App)>, BlurtError> {
+        let conn = self.connect().await?;
+        let apps = conn.call(|db_conn| {
+            let mut stmt = db_conn.prepare(
+                "SELECT * FROM app ORDER BY identifier IS NULL, identifier, app_id"
+            )?;
+            let apps = stmt.query_map([], |row| Ok((row.get("app_id")?, app_from_row(row)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(apps)
+        }).await?;
+        Ok(apps)
+    }
+
     /// Initialize the database with the notification schema
-    pub async fn init_schema(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // For in-memory databases, we need to open with the shared cache URI
-        let db_path = self.db_path.clone();
-        let conn = tokio_rusqlite::Connection::open_with_flags(db_path, OpenFlags::default()).await?;
+    ///
+    /// Creates the `app` and `record` tables from `SCHEMA`, the subset
+    /// of the macOS notification database the daemon reads. The file
+    /// is created if it doesn't exist, regardless of read-only mode.
+    pub async fn init_schema(&self) -> Result<(), BlurtError> {
+        let conn = self.open(self.db_path.clone(), OpenFlags::default()).await?;
         conn.call(|db_conn| {
             db_conn.execute_batch(SCHEMA)?;
             Ok(())
@@ -73,28 +625,177 @@ impl NotificationDatabase {
         Ok(())
     }
 
-    /// Insert a test notification record
+    /// Insert a notification record and return its `rec_id`
+    ///
+    /// `data` is the binary plist payload the daemon parses and dates
+    /// are Core Data timestamps (seconds since 2001-01-01 UTC).
+    #[allow(clippy::too_many_arguments)]
     pub async fn insert_test_notification(&self, app_id: i64, uuid: Vec<u8>, data: Vec<u8>,
                                           request_date: f64, request_last_date: f64,
                                           delivered_date: f64, presented: bool,
-                                          style: i64, snooze_fire_date: f64) -> Result<i64, Box<dyn std::error::Error>> {
+                                          style: i64, snooze_fire_date: f64) -> Result<i64, BlurtError> {
         let conn = self.connect().await?;
         let rec_id = conn.call(move |db_conn| {
-            let rec_id: i64 = db_conn.query_row(
+            db_conn.execute(
                 "INSERT INTO record (app_id, uuid, data, request_date, request_last_date,
                   delivered_date, presented, style, snooze_fire_date)
                  VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 params![app_id, uuid, data, request_date, request_last_date, delivered_date, presented, style, snooze_fire_date],
-                |row| row.get(0)
             )?;
-            Ok(rec_id)
+            Ok(db_conn.last_insert_rowid())
         }).await?;
         Ok(rec_id)
     }
 }
 
+/// A `record` row as `(ROWID, app_id, delivered date, uuid, presented, snooze fire date, data)`
+type Record = (i64, Option<i64>, Option<f64>, Option<Vec<u8>>, Option<bool>, Option<f64>, Vec<u8>);
+
+/// Parse records into notifications
+///
+/// The record's app from `apps` gives the notification's app name,
+/// and its identifier when the plist has no app field. The delivered
+/// date takes precedence over the plist's. Records that can't be
+/// parsed are skipped.
+fn parse_records(records: Vec<Record>, apps: &HashMap<i64, App>) -> Vec<Notification> {
+    let mut notifications = Vec::new();
+    for (rowid, app_id, delivered_date, uuid, presented, snooze_fire_date, bytes) in records {
+        if bytes.is_empty() {
+            continue;
+        }
+
+        let parsed = match read_plist(&bytes) {
+            Ok(plist_value) => parse_notification_from_plist(&plist_value, rowid),
+            Err(e) => {
+                warn!(rowid, error = %e, "Failed to parse as binary plist");
+                continue;
+            }
+        };
+
+        if parsed.is_empty() {
+            warn!(rowid, "Failed to parse notification data into structured format");
+        }
+
+        let app = app_id.and_then(|app_id| apps.get(&app_id));
+        for mut notification in parsed {
+            if let Some(app) = app {
+                if notification.bundle_id.is_none() {
+                    notification.bundle_id = app.identifier.clone();
+                }
+                notification.app_name = app.name.clone();
+            }
+            if let Some(delivered_date) = delivered_date {
+                notification.date = delivered_date as i64;
+            }
+            notification.uuid = uuid.as_deref().and_then(format_uuid);
+            notification.set_delivery_state(presented, snooze_fire_date);
+            notifications.push(notification);
+        }
+    }
+
+    notifications
+}
+
+/// Parse a record's `data` column as a plist, decompressing it first when gzipped
+///
+/// A few apps store their payload compressed, which is recognised
+/// by the gzip magic bytes at its start.
+pub fn read_plist(data: &[u8]) -> Result<Value, BlurtError> {
+    if !data.starts_with(&GZIP_MAGIC) {
+        return Ok(plist::from_bytes(data)?);
+    }
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decompressed)?;
+    Ok(plist::from_bytes(&decompressed)?)
+}
+
+/// Shorten text to at most `max_chars` characters, ending with an ellipsis
+///
+/// The ellipsis counts towards the limit. Characters are never split.
+pub fn truncate(value: &str, max_chars: usize) -> String {
+    if value.chars().count() <= max_chars {
+        return value.to_string();
+    }
+
+    let mut truncated: String = value.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Format a 16 byte `uuid` blob as a canonical lowercase UUID string
+///
+/// Returns `None` for blobs of any other length.
+pub fn format_uuid(bytes: &[u8]) -> Option<String> {
+    if bytes.len() != 16 {
+        return None;
+    }
+
+    let hex = hex::encode(bytes);
+    Some(format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]))
+}
+
+/// Escape the characters SQLite treats specially in a URI filename
+fn escape_uri_path(path: &str) -> String {
+    path.replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23")
+}
+
+/// An app's row in the `app` table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct App {
+    /// Bundle identifier, e.g. `com.apple.iCal`
+    pub identifier: Option<String>,
+    /// Human readable name, e.g. `Calendar`
+    pub name: Option<String>,
+}
+
+/// Look up the bundle identifier for an `app_id` in the `app` table
+pub async fn app_identifier(conn: &TokioConnection, app_id: i64) -> Result<Option<String>, BlurtError> {
+    Ok(app_info(conn, app_id).await?.and_then(|app| app.identifier))
+}
+
+/// Look up an `app_id` in the `app` table
+///
+/// Not every version of macOS stores a name for the app so it's
+/// only read when the table has a `name` column.
+pub async fn app_info(conn: &TokioConnection, app_id: i64) -> Result<Option<App>, BlurtError> {
+    let app = conn.call(move |db_conn| {
+        let app = db_conn.query_row(
+            "SELECT * FROM app WHERE app_id = ?",
+            [app_id],
+            app_from_row,
+        ).optional()?;
+        Ok(app)
+    }).await?;
+    Ok(app)
+}
+
+/// Read an `App` from a row of `SELECT * FROM app`
+fn app_from_row(row: &rusqlite::Row) -> rusqlite::Result<App> {
+    let name = match row.as_ref().column_index("name") {
+        Ok(index) => row.get::<_, Option<String>>(index)?,
+        Err(_) => None,
+    };
+    Ok(App {
+        identifier: row.get("identifier")?,
+        name,
+    })
+}
+
 /// SQL schema for the notification database
+///
+/// These are the tables and columns the daemon reads from the macOS
+/// notification database. `app` maps an `app_id` to the app's bundle
+/// identifier and each `record` row is one notification whose `data`
+/// column holds a binary plist.
 pub const SCHEMA: &str = r#"
+CREATE TABLE app (
+    app_id INTEGER PRIMARY KEY,
+    identifier VARCHAR,
+    badge INTEGER NULL
+);
 CREATE TABLE record (
     rec_id INTEGER PRIMARY KEY,
     app_id INTEGER,
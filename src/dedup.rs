@@ -0,0 +1,107 @@
+//! Deduplication of notifications that apps re-post repeatedly.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+use crate::database::Notification;
+
+/// Default time window in which identical notifications are dropped
+pub const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(60);
+
+/// What makes two notifications duplicates of each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum DedupKey {
+    /// The record's UUID, so only the same record read twice
+    Uuid,
+    /// The bundle id, title and body
+    Content,
+    /// The bundle id and thread, so one per conversation
+    Thread,
+}
+
+/// Drops notifications identical to one seen within a time window
+///
+/// What makes two notifications identical is chosen with a
+/// `DedupKey`, their content unless another key is set.
+pub struct Deduplicator {
+    window: Duration,
+    key: DedupKey,
+    seen: HashMap<u64, Instant>,
+}
+
+impl Deduplicator {
+    /// Create a deduplicator, a zero window disables deduplication
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            key: DedupKey::Content,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Choose what makes two notifications identical
+    pub fn with_key(mut self, key: DedupKey) -> Self {
+        self.key = key;
+        self.seen.clear();
+        self
+    }
+
+    /// Get what makes two notifications identical
+    pub fn key(&self) -> DedupKey {
+        self.key
+    }
+
+    /// Check if the notification was already seen within the window
+    ///
+    /// The first sighting is remembered and later identical
+    /// notifications are duplicates until the window elapses.
+    pub fn is_duplicate(&mut self, notification: &Notification) -> bool {
+        if self.window.is_zero() {
+            return false;
+        }
+
+        let key = key_hash(self.key, notification);
+        let now = Instant::now();
+        match self.seen.get(&key) {
+            Some(seen) if now.duration_since(*seen) < self.window => true,
+            _ => {
+                self.seen.insert(key, now);
+                false
+            }
+        }
+    }
+
+    /// Forget notifications last seen outside of the window
+    pub fn evict_stale(&mut self) {
+        let window = self.window;
+        self.seen.retain(|_, seen| seen.elapsed() < window);
+    }
+}
+
+/// Hash the fields that make two notifications identical under the key
+///
+/// Notifications without a UUID or thread fall back to their content,
+/// otherwise every one of them would be a duplicate of the first.
+fn key_hash(key: DedupKey, notification: &Notification) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match (key, &notification.uuid, &notification.thread_id) {
+        (DedupKey::Uuid, Some(uuid), _) => {
+            key.hash(&mut hasher);
+            uuid.hash(&mut hasher);
+        }
+        (DedupKey::Thread, _, Some(thread_id)) => {
+            key.hash(&mut hasher);
+            notification.bundle_id.hash(&mut hasher);
+            thread_id.hash(&mut hasher);
+        }
+        _ => {
+            DedupKey::Content.hash(&mut hasher);
+            notification.bundle_id.hash(&mut hasher);
+            notification.title.hash(&mut hasher);
+            notification.body.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
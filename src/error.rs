@@ -0,0 +1,58 @@
+//! Error types returned by the daemon and database.
+
+use rusqlite::ErrorCode;
+use thiserror::Error;
+
+/// Errors that can occur while reading and dispatching notifications
+#[derive(Debug, Error)]
+pub enum BlurtError {
+    /// The notification database file doesn't exist
+    #[error("database file not found: {0}")]
+    DatabaseNotFound(String),
+
+    /// Opening or querying the SQLite database failed
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] tokio_rusqlite::Error),
+
+    /// A record's data couldn't be parsed as a plist
+    #[error("failed to parse plist: {0}")]
+    PlistParse(#[from] plist::Error),
+
+    /// The file has no `record` table so it isn't a notification database
+    #[error("incompatible database {0}: there's no record table, it doesn't look like a macOS notification database")]
+    IncompatibleDatabase(String),
+
+    /// The `record` table is missing a column the daemon reads
+    #[error("incompatible database {path}: the record table has no {column} column, this version of macOS may not be supported")]
+    IncompatibleSchema { path: String, column: String },
+
+    /// Reading or writing a file or socket failed
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Watching a database file for changes failed
+    #[error("watch error: {0}")]
+    Watch(#[from] notify::Error),
+
+    /// Delivering a notification to its destination failed
+    #[error("sink error: {0}")]
+    Sink(String),
+}
+
+impl BlurtError {
+    /// Check if the error is caused by another process locking the database
+    pub fn is_busy(&self) -> bool {
+        match self {
+            BlurtError::Sqlite(tokio_rusqlite::Error::Rusqlite(rusqlite::Error::SqliteFailure(e, _))) => {
+                matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for BlurtError {
+    fn from(e: rusqlite::Error) -> Self {
+        BlurtError::Sqlite(tokio_rusqlite::Error::Rusqlite(e))
+    }
+}
@@ -0,0 +1,87 @@
+//! Write notifications to files for the `export` command.
+
+use std::io::Write;
+use crate::database::{Notification, NotificationDatabase};
+use crate::error::BlurtError;
+
+/// Columns written by `write_csv`, in order
+const CSV_COLUMNS: &[&str] = &["id", "uuid", "date", "bundle_id", "app_name", "title", "subtitle", "body", "thread_id", "source"];
+
+/// Read the notifications in every database, oldest first
+///
+/// With `since`, a Core Data timestamp, only notifications delivered
+/// after it are read.
+pub async fn read_notifications(dbs: &[NotificationDatabase], since: Option<i64>) -> Result<Vec<Notification>, BlurtError> {
+    let mut notifications = Vec::new();
+    for db in dbs {
+        if !db.exists() {
+            return Err(BlurtError::DatabaseNotFound(db.db_path().to_string()));
+        }
+
+        let mut found = match since {
+            Some(since) => db.notifications_since(since).await?,
+            None => db.all_notifications().await?,
+        };
+        for notification in &mut found {
+            notification.source = Some(db.db_path().to_string());
+        }
+        notifications.append(&mut found);
+    }
+
+    // Interleave notifications from different databases
+    notifications.sort_by_key(|notification| notification.date);
+    Ok(notifications)
+}
+
+/// Write each notification as a line of JSON
+pub fn write_ndjson(mut writer: impl Write, notifications: &[Notification]) -> Result<(), BlurtError> {
+    for notification in notifications {
+        serde_json::to_writer(&mut writer, notification)
+            .map_err(std::io::Error::from)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the notifications as CSV with a header row
+///
+/// Fields holding commas, quotes or line breaks are quoted as
+/// described in RFC 4180.
+pub fn write_csv(mut writer: impl Write, notifications: &[Notification]) -> Result<(), BlurtError> {
+    write_csv_row(&mut writer, CSV_COLUMNS.iter().copied())?;
+    for notification in notifications {
+        let id = notification.id.to_string();
+        let date = notification.date.to_string();
+        write_csv_row(&mut writer, [
+            id.as_str(),
+            notification.uuid.as_deref().unwrap_or_default(),
+            date.as_str(),
+            notification.bundle_id.as_deref().unwrap_or_default(),
+            notification.app_name.as_deref().unwrap_or_default(),
+            notification.title.as_str(),
+            notification.subtitle.as_deref().unwrap_or_default(),
+            notification.body.as_str(),
+            notification.thread_id.as_deref().unwrap_or_default(),
+            notification.source.as_deref().unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write one CSV row terminated by CRLF
+fn write_csv_row<'a>(writer: &mut impl Write, fields: impl IntoIterator<Item = &'a str>) -> Result<(), BlurtError> {
+    let row: Vec<String> = fields.into_iter().map(escape_csv).collect();
+    write!(writer, "{}\r\n", row.join(","))?;
+    Ok(())
+}
+
+/// Quote a field when it holds characters that are special in CSV
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,309 @@
+//! Filter expressions combining conditions on a notification's fields.
+//!
+//! An expression compares fields to quoted strings and combines the
+//! comparisons, e.g. `bundle_id == "com.apple.iCal" && body ~ "meeting"`.
+//! Comparisons are `==`, `!=` and `~` for a regex match, combined with
+//! `&&` and `||` and grouped with parentheses. `&&` binds tighter
+//! than `||`.
+
+use std::fmt;
+use std::str::FromStr;
+use regex::Regex;
+use crate::database::Notification;
+
+/// A notification field a filter can compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Subtitle,
+    Body,
+    BundleId,
+    AppName,
+}
+
+impl Field {
+    /// Get the field's value, `None` when the notification doesn't have it
+    fn value<'a>(&self, notification: &'a Notification) -> Option<&'a str> {
+        match self {
+            Self::Title => Some(&notification.title),
+            Self::Subtitle => notification.subtitle.as_deref(),
+            Self::Body => Some(&notification.body),
+            Self::BundleId => notification.bundle_id.as_deref(),
+            Self::AppName => notification.app_name.as_deref(),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(Self::Title),
+            "subtitle" => Ok(Self::Subtitle),
+            "body" => Ok(Self::Body),
+            "bundle_id" => Ok(Self::BundleId),
+            "app_name" => Ok(Self::AppName),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A parsed filter expression
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Equals(Field, String),
+    NotEquals(Field, String),
+    Matches(Field, Regex),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Check if the notification satisfies the expression
+    ///
+    /// A missing field is never equal to or matched by anything.
+    pub fn matches(&self, notification: &Notification) -> bool {
+        match self {
+            Self::Equals(field, value) => field.value(notification) == Some(value.as_str()),
+            Self::NotEquals(field, value) => field.value(notification) != Some(value.as_str()),
+            Self::Matches(field, regex) => field.value(notification).is_some_and(|value| regex.is_match(value)),
+            Self::And(left, right) => left.matches(notification) && right.matches(notification),
+            Self::Or(left, right) => left.matches(notification) || right.matches(notification),
+        }
+    }
+}
+
+/// A filter expression along with the text it was parsed from
+#[derive(Debug, Clone)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl Filter {
+    /// Check if the notification passes the filter
+    pub fn matches(&self, notification: &Notification) -> bool {
+        self.expr.matches(notification)
+    }
+
+    /// Get the parsed expression
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// Parse an expression, the error points at the offending token
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s).map_err(|e| e.render(s))?;
+        let mut parser = Parser { tokens, position: 0, end: s.len() };
+        let expr = parser.parse().map_err(|e| e.render(s))?;
+        Ok(Self { source: s.to_string(), expr })
+    }
+}
+
+/// Why an expression couldn't be parsed and where
+#[derive(Debug)]
+struct ParseError {
+    message: String,
+    /// Byte offset of the offending token
+    offset: usize,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, offset: usize) -> Self {
+        Self { message: message.into(), offset }
+    }
+
+    /// Show the message with the expression and a caret under the token
+    fn render(&self, source: &str) -> String {
+        let column = source[..self.offset].chars().count();
+        format!("{} at column {}\n  {}\n  {}^", self.message, column + 1, source, " ".repeat(column))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Str(String),
+    Eq,
+    NotEq,
+    Tilde,
+    And,
+    Or,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ident(name) => write!(f, "'{}'", name),
+            Self::Str(value) => write!(f, "\"{}\"", value),
+            Self::Eq => f.write_str("'=='"),
+            Self::NotEq => f.write_str("'!='"),
+            Self::Tilde => f.write_str("'~'"),
+            Self::And => f.write_str("'&&'"),
+            Self::Or => f.write_str("'||'"),
+            Self::LParen => f.write_str("'('"),
+            Self::RParen => f.write_str("')'"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    offset: usize,
+}
+
+/// Split an expression into tokens
+fn tokenize(s: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '~' => TokenKind::Tilde,
+            '=' | '!' | '&' | '|' => {
+                let (second, kind) = match c {
+                    '=' => ('=', TokenKind::Eq),
+                    '!' => ('=', TokenKind::NotEq),
+                    '&' => ('&', TokenKind::And),
+                    _ => ('|', TokenKind::Or),
+                };
+                if chars.next_if(|(_, next)| *next == second).is_none() {
+                    return Err(ParseError::new(format!("unknown operator '{}', expected '{}{}'", c, c, second), offset));
+                }
+                kind
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => value.push(escaped),
+                            None => return Err(ParseError::new("unterminated string", offset)),
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => return Err(ParseError::new("unterminated string", offset)),
+                    }
+                }
+                TokenKind::Str(value)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some((_, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                TokenKind::Ident(name)
+            }
+            c => return Err(ParseError::new(format!("unexpected character '{}'", c), offset)),
+        };
+        tokens.push(Token { kind, offset });
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of an expression
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Offset reported for errors at the end of the expression
+    end: usize,
+}
+
+impl Parser {
+    /// Parse the whole expression, failing on anything left over
+    fn parse(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.or()?;
+        match self.tokens.get(self.position) {
+            Some(token) => Err(ParseError::new(format!("unexpected {}, expected '&&' or '||'", token.kind), token.offset)),
+            None => Ok(expr),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+        while self.eat(&TokenKind::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+        while self.eat(&TokenKind::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.primary()?));
+        }
+        Ok(expr)
+    }
+
+    /// A parenthesized expression or a comparison
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        if self.eat(&TokenKind::LParen) {
+            let expr = self.or()?;
+            let (kind, offset) = self.next("')'")?;
+            if kind != TokenKind::RParen {
+                return Err(ParseError::new(format!("unexpected {}, expected ')'", kind), offset));
+            }
+            return Ok(expr);
+        }
+
+        let (kind, offset) = self.next("a field")?;
+        let TokenKind::Ident(name) = kind else {
+            return Err(ParseError::new(format!("unexpected {}, expected a field", kind), offset));
+        };
+        let field: Field = name.parse().map_err(|_| ParseError::new(
+            format!("unknown field '{}', expected title, subtitle, body, bundle_id or app_name", name),
+            offset,
+        ))?;
+
+        let (operator, offset) = self.next("'==', '!=' or '~'")?;
+        if !matches!(operator, TokenKind::Eq | TokenKind::NotEq | TokenKind::Tilde) {
+            return Err(ParseError::new(format!("unexpected {}, expected '==', '!=' or '~'", operator), offset));
+        }
+
+        let (value, offset) = self.next("a quoted string")?;
+        let TokenKind::Str(value) = value else {
+            return Err(ParseError::new(format!("unexpected {}, expected a quoted string", value), offset));
+        };
+
+        match operator {
+            TokenKind::Eq => Ok(Expr::Equals(field, value)),
+            TokenKind::NotEq => Ok(Expr::NotEquals(field, value)),
+            _ => Regex::new(&value)
+                .map(|regex| Expr::Matches(field, regex))
+                .map_err(|e| ParseError::new(format!("invalid regex: {}", e), offset)),
+        }
+    }
+
+    /// Consume the next token if it's the expected one
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        let matched = self.tokens.get(self.position).is_some_and(|token| token.kind == *kind);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    /// Take the next token, failing at the end of the expression
+    fn next(&mut self, expected: &str) -> Result<(TokenKind, usize), ParseError> {
+        let token = self.tokens.get(self.position)
+            .ok_or_else(|| ParseError::new(format!("unexpected end of expression, expected {}", expected), self.end))?;
+        self.position += 1;
+        Ok((token.kind.clone(), token.offset))
+    }
+}
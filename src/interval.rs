@@ -0,0 +1,68 @@
+//! Poll interval that adapts to how busy the notification database is.
+
+use std::time::Duration;
+use tracing::debug;
+
+/// Default factor the interval grows by while idle
+pub const DEFAULT_GROWTH: f64 = 2.0;
+
+/// Consecutive idle checks before the interval grows
+const IDLE_CHECKS_BEFORE_GROWTH: u32 = 3;
+
+/// Time to wait between checks for new notifications
+///
+/// After a check finds new notifications the interval drops to the
+/// minimum so bursts are picked up quickly. Every few consecutive idle
+/// checks it grows by the growth factor, up to the maximum. A minimum
+/// equal to the maximum gives a fixed interval.
+#[derive(Debug, Clone)]
+pub struct PollInterval {
+    min: Duration,
+    max: Duration,
+    growth: f64,
+    current: Duration,
+    idle_checks: u32,
+}
+
+impl PollInterval {
+    /// Always wait the same amount of time between checks
+    pub fn fixed(interval: Duration) -> Self {
+        Self::adaptive(interval, interval, DEFAULT_GROWTH)
+    }
+
+    /// Wait between `min` and `max` depending on recent activity
+    pub fn adaptive(min: Duration, max: Duration, growth: f64) -> Self {
+        Self {
+            min,
+            max: max.max(min),
+            growth: growth.max(1.0),
+            current: min,
+            idle_checks: 0,
+        }
+    }
+
+    /// Get the time to wait before the next check
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// Update the interval after a check
+    pub fn record_check(&mut self, found_notifications: bool) {
+        let previous = self.current;
+
+        if found_notifications {
+            self.idle_checks = 0;
+            self.current = self.min;
+        } else {
+            self.idle_checks += 1;
+            if self.idle_checks >= IDLE_CHECKS_BEFORE_GROWTH {
+                self.idle_checks = 0;
+                self.current = self.current.mul_f64(self.growth).min(self.max);
+            }
+        }
+
+        if self.current != previous {
+            debug!(?previous, current = ?self.current, "Poll interval changed");
+        }
+    }
+}
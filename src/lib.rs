@@ -2,5 +2,24 @@
 //!
 //! This daemon reads notifications from the system's SQLite database on macOS.
 
+pub mod cli;
+pub mod config;
 pub mod database;
 pub mod daemon;
+pub mod dedup;
+pub mod error;
+pub mod export;
+pub mod filter;
+pub mod interval;
+pub mod metrics;
+pub mod rate_limit;
+pub mod recent;
+pub mod schedule;
+pub mod seen;
+pub mod server;
+pub mod service;
+pub mod snapshot;
+pub mod sink;
+pub mod stats;
+pub mod timezone;
+pub mod watch;
@@ -1,30 +1,551 @@
-use blurt::daemon::NotificationDaemon;
-use std::env;
+use blurt::cli::{default_db_path, Args, Command, ExportFormat, Format};
+use blurt::config::{default_config_path, Config};
+use blurt::daemon::{parse_notification_from_plist, NotificationDaemon};
+use blurt::database::{NotificationDatabase, CORE_DATA_EPOCH};
+use blurt::error::BlurtError;
+use blurt::recent::RecentNotifications;
+use blurt::server;
+use blurt::service;
+use blurt::seen::SeenUuids;
+use blurt::timezone::DisplayTimezone;
+use blurt::sink::{DryRunSink, FileSink, NativeNotifySink, NotificationSink, SseSink, StatsdSink, StdoutSink};
+#[cfg(feature = "discord")]
+use blurt::sink::DiscordSink;
+#[cfg(feature = "email")]
+use blurt::sink::EmailSink;
+#[cfg(feature = "grpc")]
+use blurt::sink::GrpcSink;
+#[cfg(feature = "kafka")]
+use blurt::sink::KafkaSink;
+#[cfg(feature = "mqtt")]
+use blurt::sink::MqttSink;
+#[cfg(feature = "nats")]
+use blurt::sink::NatsSink;
+#[cfg(feature = "ntfy")]
+use blurt::sink::NtfySink;
+#[cfg(feature = "pushover")]
+use blurt::sink::PushoverSink;
+#[cfg(feature = "redis")]
+use blurt::sink::RedisSink;
+#[cfg(feature = "slack")]
+use blurt::sink::SlackSink;
+#[cfg(feature = "syslog")]
+use blurt::sink::SyslogSink;
+#[cfg(feature = "telegram")]
+use blurt::sink::TelegramSink;
+#[cfg(unix)]
+use blurt::sink::UnixSocketSink;
+#[cfg(feature = "webhook")]
+use blurt::sink::WebhookSink;
+#[cfg(feature = "websocket")]
+use blurt::sink::WebSocketSink;
+use chrono::{DateTime, Utc};
+use clap::{CommandFactory, FromArgMatches, Parser};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
-    let home_dir = std::env::var("HOME").unwrap();
-    let db_path = format!("{}/Library/Group Containers/group.com.apple.usernoted/db2/db", home_dir);
+    // -v and -q win over RUST_LOG since they were passed explicitly
+    let filter = match args.log_level() {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+
+    // Logs go to stderr so stdout stays reserved for notification output
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+
+    // An explicit config file has to exist but the default one is optional
+    let config_path = match args.config.clone() {
+        Some(path) => Some(path),
+        None => default_config_path().filter(|path| path.exists()),
+    };
+    if let Some(config_path) = config_path {
+        Config::load(&config_path)?.apply(&mut args, &matches)?;
+    }
+
+    match args.command.take() {
+        Some(Command::Stats { db_paths, format }) => {
+            return print_stats(or_default_db_path(db_paths)?, format).await;
+        }
+        Some(Command::Apps { db_paths, format }) => {
+            return print_apps(or_default_db_path(db_paths)?, format).await;
+        }
+        Some(Command::Export { db_paths, format, out, since }) => {
+            return export(or_default_db_path(db_paths)?, format, &out, since).await;
+        }
+        Some(Command::Parse { file, format }) => {
+            return print_parsed(&file, format, args.display_timezone(), args.color.enabled());
+        }
+        Some(Command::InstallService { load, label, args: daemon_args }) => {
+            return install_service(&label, daemon_args, load);
+        }
+        Some(Command::UninstallService { label }) => {
+            let path = service::uninstall(&label)?;
+            println!("Removed {}", path.display());
+            return Ok(());
+        }
+        None => {}
+    }
 
-    let mut daemon = if args.len() >= 2 {
-        if !cfg!(feature = "webhook") {
-            panic!("Webhook feature was enabled but no forwarding URL was provided. Rebuild without --features webhook if you want to emit json to stdout.");
+    let timezone = args.display_timezone();
+    let db_paths = or_default_db_path(args.db_paths)?;
+
+    // Shared by the daemon and the sinks that catch clients up
+    let recent = Arc::new(RecentNotifications::new(args.recent));
+
+    // Every output that's set receives each notification
+    let mut sinks: Vec<Box<dyn NotificationSink>> = Vec::new();
+    if args.syslog {
+        #[cfg(feature = "syslog")]
+        {
+            let facility: syslog::Facility = args.syslog_facility.parse()
+                .map_err(|_| format!("Invalid --syslog-facility value '{}'", args.syslog_facility))?;
+            sinks.push(Box::new(SyslogSink::new(facility)));
         }
+        #[cfg(not(feature = "syslog"))]
+        {
+            return Err("Syslog feature is not enabled. Rebuild with --features syslog".into());
+        }
+    }
+    if let Some(log_file) = args.log_file {
+        sinks.push(Box::new(FileSink::new(log_file, args.log_max_size)?));
+    }
+    if args.notify {
+        sinks.push(Box::new(NativeNotifySink));
+    }
+    if let Some(webhook_url) = args.webhook {
         #[cfg(feature = "webhook")]
         {
-            NotificationDaemon::with_webhook(&db_path, args[1].clone())
+            sinks.push(Box::new(WebhookSink::new(webhook_url)));
         }
         #[cfg(not(feature = "webhook"))]
         {
-            panic!("Webhook feature is not enabled. Rebuild with --features webhook");
+            let _ = webhook_url;
+            return Err("Webhook feature is not enabled. Rebuild with --features webhook".into());
+        }
+    }
+    if let (Some(token), Some(chat_id)) = (args.telegram_token, args.telegram_chat) {
+        #[cfg(feature = "telegram")]
+        {
+            sinks.push(Box::new(TelegramSink::new(token, chat_id)));
+        }
+        #[cfg(not(feature = "telegram"))]
+        {
+            let _ = (token, chat_id);
+            return Err("Telegram feature is not enabled. Rebuild with --features telegram".into());
+        }
+    }
+    if let Some(mqtt_url) = args.mqtt_url {
+        #[cfg(feature = "mqtt")]
+        {
+            sinks.push(Box::new(MqttSink::new(&mqtt_url, args.mqtt_topic)?));
+        }
+        #[cfg(not(feature = "mqtt"))]
+        {
+            let _ = mqtt_url;
+            return Err("MQTT feature is not enabled. Rebuild with --features mqtt".into());
+        }
+    }
+    if let Some(ws_addr) = args.ws_addr {
+        #[cfg(feature = "websocket")]
+        {
+            let listener = TcpListener::bind(&ws_addr).await
+                .map_err(|e| format!("Failed to bind --ws-addr {}: {}", ws_addr, e))?;
+            sinks.push(Box::new(WebSocketSink::new(listener, recent.clone())));
+        }
+        #[cfg(not(feature = "websocket"))]
+        {
+            let _ = ws_addr;
+            return Err("WebSocket feature is not enabled. Rebuild with --features websocket".into());
+        }
+    }
+    if let Some(sse_addr) = args.sse_addr {
+        let listener = TcpListener::bind(&sse_addr).await
+            .map_err(|e| format!("Failed to bind --sse-addr {}: {}", sse_addr, e))?;
+        sinks.push(Box::new(SseSink::new(listener, recent.clone())));
+    }
+    if let Some(grpc_addr) = args.grpc_addr {
+        #[cfg(feature = "grpc")]
+        {
+            let listener = TcpListener::bind(&grpc_addr).await
+                .map_err(|e| format!("Failed to bind --grpc-addr {}: {}", grpc_addr, e))?;
+            sinks.push(Box::new(GrpcSink::new(listener, recent.clone())));
+        }
+        #[cfg(not(feature = "grpc"))]
+        {
+            let _ = grpc_addr;
+            return Err("gRPC feature is not enabled. Rebuild with --features grpc".into());
+        }
+    }
+    if let Some(unix_socket) = args.unix_socket {
+        #[cfg(unix)]
+        {
+            sinks.push(Box::new(UnixSocketSink::new(unix_socket)));
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = unix_socket;
+            return Err("Unix sockets are only supported on Unix platforms".into());
+        }
+    }
+    if let (Some(smtp_url), Some(email_from)) = (args.smtp_url, args.email_from) {
+        #[cfg(feature = "email")]
+        {
+            sinks.push(Box::new(EmailSink::new(&smtp_url, &email_from, &args.email_to)?));
+        }
+        #[cfg(not(feature = "email"))]
+        {
+            let _ = (smtp_url, email_from);
+            return Err("Email feature is not enabled. Rebuild with --features email".into());
+        }
+    }
+    if let Some(ntfy_url) = args.ntfy_url {
+        #[cfg(feature = "ntfy")]
+        {
+            sinks.push(Box::new(NtfySink::new(ntfy_url, args.ntfy_token)));
+        }
+        #[cfg(not(feature = "ntfy"))]
+        {
+            let _ = ntfy_url;
+            return Err("ntfy feature is not enabled. Rebuild with --features ntfy".into());
+        }
+    }
+    if let Some(discord_webhook) = args.discord_webhook {
+        #[cfg(feature = "discord")]
+        {
+            sinks.push(Box::new(DiscordSink::new(discord_webhook)));
+        }
+        #[cfg(not(feature = "discord"))]
+        {
+            let _ = discord_webhook;
+            return Err("discord feature is not enabled. Rebuild with --features discord".into());
+        }
+    }
+    if let Some(slack_webhook) = args.slack_webhook {
+        #[cfg(feature = "slack")]
+        {
+            sinks.push(Box::new(SlackSink::new(slack_webhook, args.slack_channel)));
+        }
+        #[cfg(not(feature = "slack"))]
+        {
+            let _ = slack_webhook;
+            return Err("slack feature is not enabled. Rebuild with --features slack".into());
+        }
+    }
+    if let Some(nats_url) = args.nats_url {
+        #[cfg(feature = "nats")]
+        {
+            sinks.push(Box::new(NatsSink::connect(&nats_url, args.nats_subject).await?));
         }
+        #[cfg(not(feature = "nats"))]
+        {
+            let _ = nats_url;
+            return Err("NATS feature is not enabled. Rebuild with --features nats".into());
+        }
+    }
+    if let Some(redis_url) = args.redis_url {
+        #[cfg(feature = "redis")]
+        {
+            sinks.push(Box::new(RedisSink::connect(&redis_url, args.redis_channel, args.redis_mode).await?));
+        }
+        #[cfg(not(feature = "redis"))]
+        {
+            let _ = redis_url;
+            return Err("Redis feature is not enabled. Rebuild with --features redis".into());
+        }
+    }
+    if let (Some(pushover_token), Some(pushover_user)) = (args.pushover_token, args.pushover_user) {
+        #[cfg(feature = "pushover")]
+        {
+            sinks.push(Box::new(PushoverSink::new(pushover_token, pushover_user, args.pushover_priority)));
+        }
+        #[cfg(not(feature = "pushover"))]
+        {
+            let _ = (pushover_token, pushover_user);
+            return Err("Pushover feature is not enabled. Rebuild with --features pushover".into());
+        }
+    }
+    if let (Some(kafka_brokers), Some(kafka_topic)) = (args.kafka_brokers, args.kafka_topic) {
+        #[cfg(feature = "kafka")]
+        {
+            sinks.push(Box::new(KafkaSink::new(&kafka_brokers, kafka_topic)?));
+        }
+        #[cfg(not(feature = "kafka"))]
+        {
+            let _ = (kafka_brokers, kafka_topic);
+            return Err("Kafka feature is not enabled. Rebuild with --features kafka".into());
+        }
+    }
+    if sinks.is_empty() {
+        let mut stdout = StdoutSink::default().with_pretty(args.json_pretty);
+        if args.text {
+            stdout = stdout.with_text(timezone, args.color.enabled());
+        }
+        sinks.push(Box::new(stdout));
+    }
+    if args.dry_run {
+        sinks = sinks.into_iter()
+            .map(|sink| Box::new(DryRunSink::new(sink)) as Box<dyn NotificationSink>)
+            .collect();
+    }
+    // Metrics don't replace stdout as the output and are still sent
+    // in a dry run, they only count what would have been delivered
+    if let Some(statsd_addr) = args.statsd_addr {
+        let sink = StatsdSink::new(&statsd_addr, &args.statsd_prefix)
+            .map_err(|e| format!("Invalid --statsd-addr {}: {}", statsd_addr, e))?;
+        sinks.push(Box::new(sink));
+    }
+
+    // Never risk writing to the live notification database. Immutable
+    // connections skip locking entirely but only see what's been
+    // checkpointed into the main file, so --wal-mode trades that for
+    // plain read-only ones that also read the write-ahead log.
+    let mut daemon = NotificationDaemon::new(&db_paths[0].to_string_lossy());
+    for db_path in &db_paths[1..] {
+        daemon = daemon.with_database(&db_path.to_string_lossy());
+    }
+
+    let mut daemon = daemon
+        .with_sinks(sinks)
+        .with_immutable(!args.wal_mode)
+        .with_snapshot(args.snapshot)
+        .with_poll_interval(Duration::from_secs(args.interval))
+        .with_watch(args.watch)
+        .with_watch_updates(args.watch_updates)
+        .with_from_beginning(args.from_beginning)
+        .with_include_raw(args.include_raw)
+        .with_include_metadata(args.include_metadata)
+        .with_fields(args.fields)
+        .with_delivered_only(args.delivered_only)
+        .with_batching(args.batch)
+        .with_allowlist(args.allowlist)
+        .with_blocklist(args.blocklist)
+        .with_title_patterns(args.match_title)
+        .with_body_patterns(args.match_body)
+        .with_redact_patterns(args.redact)
+        .with_hash_body(args.hash_body)
+        .with_redact_title(args.redact_title)
+        .with_dedup_window(Duration::from_secs(args.dedup_window))
+        .with_dedup_key(args.dedup_key)
+        .with_busy_timeout(Duration::from_millis(args.busy_timeout))
+        .with_busy_retries(args.busy_retries)
+        .with_max_consecutive_errors(args.max_errors)
+        .with_recent(recent);
+    if let Some(max_interval) = args.max_interval {
+        if max_interval < args.interval {
+            return Err("--max-interval must be at least --interval".into());
+        }
+        if args.interval_growth < 1.0 {
+            return Err("--interval-growth must be at least 1".into());
+        }
+        daemon = daemon.with_adaptive_polling(
+            Duration::from_secs(args.interval),
+            Duration::from_secs(max_interval),
+            args.interval_growth,
+        );
+    }
+    if let Some(since_rowid) = args.since_rowid {
+        daemon = daemon.with_start_rowid(since_rowid);
+    }
+    if let Some(max_runtime) = args.max_runtime {
+        daemon = daemon.with_max_runtime(Duration::from_secs(max_runtime));
+    }
+    if let Some(max_notifications) = args.max_notifications {
+        daemon = daemon.with_max_notifications(max_notifications);
+    }
+    if let Some(max_body) = args.max_body {
+        daemon = daemon.with_max_body(max_body as usize);
+    }
+    if let Some(filter) = args.filter {
+        daemon = daemon.with_filter(filter);
+    }
+    if let Some(rate) = args.rate {
+        daemon = daemon.with_rate_limit(rate);
+    }
+    if let Some(quiet_hours) = args.quiet {
+        daemon = daemon.with_quiet_hours(quiet_hours, args.quiet_queue);
+    }
+    if let Some(path) = args.seen_file {
+        let seen = SeenUuids::load(&path, args.seen_capacity as usize)
+            .map_err(|e| format!("Can't read --seen-file {}: {}", path.display(), e))?;
+        info!(path = %path.display(), count = seen.len(), "Loaded seen UUIDs");
+        daemon = daemon.with_seen_uuids(seen);
+    }
+    if let Some(dir) = args.dump_failures {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Can't create --dump-failures {}: {}", dir.display(), e))?;
+        daemon = daemon.on_parse_error(move |rowid, data| {
+            let path = dir.join(format!("{}.plist", rowid));
+            match std::fs::write(&path, data) {
+                Ok(()) => info!(rowid, path = %path.display(), "Saved notification data that failed to parse"),
+                Err(e) => warn!(rowid, path = %path.display(), error = %e, "Failed to save notification data"),
+            }
+        });
+    }
+    let metrics_server = match args.metrics_addr {
+        Some(addr) => {
+            let listener = TcpListener::bind(&addr).await
+                .map_err(|e| format!("Failed to bind --metrics-addr {}: {}", addr, e))?;
+            // Adaptive polling can legitimately wait up to the longest interval
+            let poll_interval = Duration::from_secs(args.max_interval.unwrap_or(args.interval));
+            Some(tokio::spawn(server::serve(listener, daemon.metrics(), daemon.recent(), poll_interval * args.stale_polls)))
+        }
+        None => None,
+    };
+
+    let result = if args.once {
+        daemon.run_once().await
+    } else if let Some(count) = args.tail {
+        daemon.run_tail(count).await
     } else {
-        NotificationDaemon::new(&db_path)
+        daemon.start().await
     };
 
-    daemon.start().await?;
+    // The daemon only returns on shutdown so stop serving metrics too
+    if let Some(metrics_server) = metrics_server {
+        metrics_server.abort();
+    }
+    result?;
+
+    Ok(())
+}
+
+/// Fall back to the current user's notification database when no paths are given
+fn or_default_db_path(mut db_paths: Vec<PathBuf>) -> Result<Vec<PathBuf>, String> {
+    if db_paths.is_empty() {
+        db_paths.push(default_db_path()?);
+    }
+    Ok(db_paths)
+}
+
+/// Print the number of notifications per app, noisiest first
+async fn print_stats(db_paths: Vec<PathBuf>, format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    let dbs: Vec<NotificationDatabase> = db_paths.iter()
+        .map(|db_path| NotificationDatabase::new(&db_path.to_string_lossy()).with_immutable(true))
+        .collect();
+    let counts = blurt::stats::app_counts(&dbs).await?;
+
+    match format {
+        Format::Text => {
+            for app_count in &counts {
+                println!("{:>8}  {}", app_count.count, app_count.bundle_id.as_deref().unwrap_or("(unknown)"));
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string(&counts)?),
+    }
+
+    Ok(())
+}
+
+/// Print the apps of every database sorted by bundle identifier
+async fn print_apps(db_paths: Vec<PathBuf>, format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    let mut apps = Vec::new();
+    for db_path in &db_paths {
+        let db = NotificationDatabase::new(&db_path.to_string_lossy()).with_immutable(true);
+        if !db.exists() {
+            return Err(BlurtError::DatabaseNotFound(db.db_path().to_string()).into());
+        }
+        apps.extend(db.list_apps().await?);
+    }
+    // Databases of the same user list the same apps
+    apps.sort_by(|(_, a), (_, b)| a.identifier.is_none().cmp(&b.identifier.is_none()).then_with(|| a.identifier.cmp(&b.identifier)));
+    apps.dedup_by(|(_, a), (_, b)| a == b);
+
+    match format {
+        Format::Text => {
+            for (_, app) in &apps {
+                let identifier = app.identifier.as_deref().unwrap_or("(unknown)");
+                match &app.name {
+                    Some(name) => println!("{:<48}  {}", identifier, name),
+                    None => println!("{}", identifier),
+                }
+            }
+        }
+        Format::Json => {
+            let apps: Vec<_> = apps.iter()
+                .map(|(app_id, app)| serde_json::json!({
+                    "app_id": app_id,
+                    "identifier": app.identifier,
+                    "name": app.name,
+                }))
+                .collect();
+            println!("{}", serde_json::to_string(&apps)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write every notification delivered since the date to a file
+async fn export(db_paths: Vec<PathBuf>, format: ExportFormat, out: &Path, since: Option<DateTime<Utc>>) -> Result<(), Box<dyn std::error::Error>> {
+    let dbs: Vec<NotificationDatabase> = db_paths.iter()
+        .map(|db_path| NotificationDatabase::new(&db_path.to_string_lossy()).with_immutable(true))
+        .collect();
+    let since = since.map(|since| since.timestamp() - CORE_DATA_EPOCH);
+    let notifications = blurt::export::read_notifications(&dbs, since).await?;
+
+    let file = std::fs::File::create(out)
+        .map_err(|e| format!("Can't create {}: {}", out.display(), e))?;
+    let writer = std::io::BufWriter::new(file);
+    match format {
+        ExportFormat::Ndjson => blurt::export::write_ndjson(writer, &notifications)?,
+        ExportFormat::Csv => blurt::export::write_csv(writer, &notifications)?,
+    }
+
+    info!(count = notifications.len(), path = %out.display(), "Exported notifications");
+    Ok(())
+}
+
+/// Print the notifications parsed from a plist file
+fn print_parsed(file: &Path, format: Format, timezone: DisplayTimezone, color: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(file)
+        .map_err(|e| format!("Can't read {}: {}", file.display(), e))?;
+    let plist_value = blurt::database::read_plist(&bytes)
+        .map_err(|e| format!("{} isn't a plist: {}", file.display(), e))?;
+    let notifications = parse_notification_from_plist(&plist_value, 0);
+    if notifications.is_empty() {
+        return Err(format!("{} doesn't hold a notification", file.display()).into());
+    }
+
+    match format {
+        Format::Text => {
+            for (index, notification) in notifications.iter().enumerate() {
+                if index > 0 {
+                    println!();
+                }
+                println!("{}", notification.describe(timezone, color));
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(&notifications)?),
+    }
+
+    Ok(())
+}
+
+/// Write the LaunchAgent running the daemon with `daemon_args`
+fn install_service(label: &str, daemon_args: Vec<String>, load: bool) -> Result<(), Box<dyn std::error::Error>> {
+    // Catch typos now rather than when launchd starts the agent
+    let parsed = Args::try_parse_from(std::iter::once("blurt".to_string()).chain(daemon_args.iter().cloned()))
+        .map_err(|e| format!("Invalid daemon flags: {}", e.render().to_string().trim_end()))?;
+    if parsed.command.is_some() {
+        return Err("The service can only run the daemon, pass its flags without a subcommand".into());
+    }
 
+    let path = service::install(label, &daemon_args, load)?;
+    println!("Wrote {}", path.display());
+    if !load {
+        println!("Start it with: launchctl load -w {}", path.display());
+    }
     Ok(())
 }
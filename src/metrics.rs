@@ -0,0 +1,169 @@
+//! Counters and gauges describing what the daemon is doing.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Metrics updated by the daemon and rendered for Prometheus
+#[derive(Default)]
+pub struct Metrics {
+    notifications_processed: AtomicU64,
+    parse_failures: AtomicU64,
+    rate_limited: AtomicU64,
+    last_poll_timestamp: AtomicI64,
+    last_success_timestamp: AtomicI64,
+    consecutive_errors: AtomicU32,
+    notifications_by_bundle: Mutex<HashMap<String, u64>>,
+    last_rowids: Mutex<HashMap<String, i64>>,
+    schema_versions: Mutex<HashMap<String, i64>>,
+}
+
+impl Metrics {
+    /// Record a successfully parsed notification
+    pub fn record_notification(&self, bundle_id: Option<&str>) {
+        self.notifications_processed.fetch_add(1, Ordering::Relaxed);
+        let mut by_bundle = self.notifications_by_bundle.lock().unwrap();
+        *by_bundle.entry(bundle_id.unwrap_or("unknown").to_string()).or_default() += 1;
+    }
+
+    /// Record a record whose data couldn't be parsed
+    pub fn record_parse_failure(&self) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a notification dropped for going over its app's rate
+    pub fn record_rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that a poll of the databases just happened
+    pub fn record_poll(&self) {
+        self.last_poll_timestamp.store(unix_now(), Ordering::Relaxed);
+    }
+
+    /// Record whether a poll checked every database without errors
+    pub fn record_poll_result(&self, success: bool) {
+        if success {
+            self.last_success_timestamp.store(unix_now(), Ordering::Relaxed);
+            self.consecutive_errors.store(0, Ordering::Relaxed);
+        } else {
+            self.consecutive_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record the last ROWID processed for a database
+    pub fn record_last_rowid(&self, source: &str, last_rowid: i64) {
+        self.last_rowids.lock().unwrap().insert(source.to_string(), last_rowid);
+    }
+
+    /// Record the schema version of a database
+    pub fn record_schema_version(&self, source: &str, version: i64) {
+        self.schema_versions.lock().unwrap().insert(source.to_string(), version);
+    }
+
+    /// Report whether polling is keeping up
+    ///
+    /// The daemon is healthy when the last successful poll happened
+    /// less than `stale_after` ago.
+    pub fn health(&self, stale_after: Duration) -> Health {
+        let last_success = self.last_success_timestamp.load(Ordering::Relaxed);
+        let last_poll = (last_success > 0).then_some(last_success);
+        let healthy = last_poll.is_some_and(|last_poll| unix_now() - last_poll < stale_after.as_secs() as i64);
+
+        Health {
+            healthy,
+            last_poll,
+            last_rowid: self.last_rowids.lock().unwrap().iter()
+                .map(|(source, last_rowid)| (source.clone(), *last_rowid))
+                .collect(),
+            consecutive_errors: self.consecutive_errors.load(Ordering::Relaxed),
+            schema_version: self.schema_versions.lock().unwrap().iter()
+                .map(|(source, version)| (source.clone(), *version))
+                .collect(),
+        }
+    }
+
+    /// Check if a poll has succeeded since the daemon started
+    pub fn is_ready(&self) -> bool {
+        self.last_success_timestamp.load(Ordering::Relaxed) > 0
+    }
+
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_metric(&mut out, "blurt_notifications_processed_total", "counter",
+                     "Notifications parsed from the database");
+        let _ = writeln!(out, "blurt_notifications_processed_total {}", self.notifications_processed.load(Ordering::Relaxed));
+
+        write_metric(&mut out, "blurt_parse_failures_total", "counter",
+                     "Records whose data couldn't be parsed");
+        let _ = writeln!(out, "blurt_parse_failures_total {}", self.parse_failures.load(Ordering::Relaxed));
+
+        write_metric(&mut out, "blurt_notifications_rate_limited_total", "counter",
+                     "Notifications dropped for going over their app's rate");
+        let _ = writeln!(out, "blurt_notifications_rate_limited_total {}", self.rate_limited.load(Ordering::Relaxed));
+
+        write_metric(&mut out, "blurt_notifications_by_bundle_total", "counter",
+                     "Notifications parsed per bundle id");
+        let by_bundle = self.notifications_by_bundle.lock().unwrap();
+        let mut bundles: Vec<_> = by_bundle.iter().collect();
+        bundles.sort();
+        for (bundle_id, count) in bundles {
+            let _ = writeln!(out, "blurt_notifications_by_bundle_total{{bundle_id=\"{}\"}} {}", escape_label(bundle_id), count);
+        }
+
+        write_metric(&mut out, "blurt_last_poll_timestamp_seconds", "gauge",
+                     "Unix time of the last poll");
+        let _ = writeln!(out, "blurt_last_poll_timestamp_seconds {}", self.last_poll_timestamp.load(Ordering::Relaxed));
+
+        write_metric(&mut out, "blurt_consecutive_errors", "gauge",
+                     "Polls in a row that failed");
+        let _ = writeln!(out, "blurt_consecutive_errors {}", self.consecutive_errors.load(Ordering::Relaxed));
+
+        write_metric(&mut out, "blurt_last_rowid", "gauge",
+                     "Last ROWID processed per database");
+        let last_rowids = self.last_rowids.lock().unwrap();
+        let mut sources: Vec<_> = last_rowids.iter().collect();
+        sources.sort();
+        for (source, last_rowid) in sources {
+            let _ = writeln!(out, "blurt_last_rowid{{source=\"{}\"}} {}", escape_label(source), last_rowid);
+        }
+
+        out
+    }
+}
+
+/// Health of the daemon as reported by the health endpoint
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct Health {
+    pub healthy: bool,
+    /// Unix time of the last successful poll, `None` before the first
+    pub last_poll: Option<i64>,
+    /// Last ROWID processed per database
+    pub last_rowid: BTreeMap<String, i64>,
+    pub consecutive_errors: u32,
+    /// `PRAGMA user_version` per database, once its schema was checked
+    pub schema_version: BTreeMap<String, i64>,
+}
+
+/// Current Unix time in seconds
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Write the HELP and TYPE lines for a metric
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,94 @@
+//! Per-app rate limiting so one noisy app can't flood the sinks.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How many notifications an app may send in a period, e.g. `10/min`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    count: u32,
+    period: Duration,
+}
+
+impl Rate {
+    /// Allow `count` notifications every `period`
+    pub fn new(count: u32, period: Duration) -> Self {
+        Self { count, period }
+    }
+}
+
+impl FromStr for Rate {
+    type Err = String;
+
+    /// Parse a rate written as `COUNT/UNIT`, the unit being `sec`, `min`, `hour` or `day`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, unit) = s.split_once('/')
+            .ok_or_else(|| format!("Invalid rate '{}': expected COUNT/UNIT, e.g. 10/min", s))?;
+        let count: u32 = count.trim().parse()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| format!("Invalid count '{}' in rate '{}': expected a positive number", count, s))?;
+        let period = match unit.trim() {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(60 * 60),
+            "d" | "day" => Duration::from_secs(24 * 60 * 60),
+            _ => return Err(format!("Invalid unit '{}' in rate '{}': expected sec, min, hour or day", unit, s)),
+        };
+
+        Ok(Self::new(count, period))
+    }
+}
+
+/// Token bucket of a single app
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Drops notifications from apps sending more than their rate
+///
+/// Each bundle identifier gets its own token bucket holding up to
+/// the rate's count, refilled steadily over its period. Notifications
+/// without a bundle identifier share a bucket.
+pub struct RateLimiter {
+    rate: Rate,
+    buckets: HashMap<Option<String>, Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing each app the given rate
+    pub fn new(rate: Rate) -> Self {
+        Self {
+            rate,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Check if the app may send another notification, using up a token if so
+    pub fn allow(&mut self, bundle_id: Option<&str>) -> bool {
+        let now = Instant::now();
+        let capacity = f64::from(self.rate.count);
+        let refill_per_sec = capacity / self.rate.period.as_secs_f64();
+
+        let bucket = self.buckets.entry(bundle_id.map(str::to_string))
+            .or_insert(Bucket { tokens: capacity, updated: now });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Forget apps idle long enough for their bucket to be full again
+    pub fn evict_idle(&mut self) {
+        let period = self.rate.period;
+        self.buckets.retain(|_, bucket| bucket.updated.elapsed() < period);
+    }
+}
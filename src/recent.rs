@@ -0,0 +1,89 @@
+//! Bounded history of the notifications the daemon dispatched.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use crate::database::Notification;
+
+/// Default number of notifications kept in the history
+pub const DEFAULT_RECENT_CAPACITY: usize = 100;
+
+/// The most recently dispatched notifications, oldest first
+///
+/// Shared with the HTTP server so a dashboard can fetch recent
+/// history without querying the database again, and with the
+/// streaming sinks so clients can catch up on what they missed.
+/// Once full the oldest notification is dropped for each new one.
+///
+/// Every notification pushed gets the next id, starting from 1,
+/// even when the capacity is zero and it isn't kept.
+#[derive(Debug)]
+pub struct RecentNotifications {
+    capacity: usize,
+    buffer: Mutex<Buffer>,
+}
+
+#[derive(Debug)]
+struct Buffer {
+    entries: VecDeque<(u64, Notification)>,
+    last_id: u64,
+}
+
+impl RecentNotifications {
+    /// Keep up to `capacity` notifications, zero keeps none
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            buffer: Mutex::new(Buffer {
+                entries: VecDeque::with_capacity(capacity),
+                last_id: 0,
+            }),
+        }
+    }
+
+    /// Get the most notifications kept at once
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Remember a notification, dropping the oldest when full, and return its id
+    pub fn push(&self, notification: &Notification) -> u64 {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.last_id += 1;
+        let id = buffer.last_id;
+        if self.capacity == 0 {
+            return id;
+        }
+
+        if buffer.entries.len() == self.capacity {
+            buffer.entries.pop_front();
+        }
+        buffer.entries.push_back((id, notification.clone()));
+        id
+    }
+
+    /// Get the id of the last notification pushed, 0 before the first
+    pub fn last_id(&self) -> u64 {
+        self.buffer.lock().unwrap().last_id
+    }
+
+    /// Copy the notifications currently kept, oldest first
+    pub fn snapshot(&self) -> Vec<Notification> {
+        self.buffer.lock().unwrap().entries.iter()
+            .map(|(_, notification)| notification.clone())
+            .collect()
+    }
+
+    /// Copy the notifications kept that were pushed after `id` along with their ids
+    pub fn since(&self, id: u64) -> Vec<(u64, Notification)> {
+        self.buffer.lock().unwrap().entries.iter()
+            .filter(|(entry_id, _)| *entry_id > id)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for RecentNotifications {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT_CAPACITY)
+    }
+}
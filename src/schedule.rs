@@ -0,0 +1,49 @@
+//! Quiet hours during which notifications aren't dispatched.
+
+use chrono::{Local, NaiveTime};
+use std::str::FromStr;
+
+/// A daily window of local time in which dispatch is suppressed
+///
+/// The window includes its start and excludes its end. A start
+/// later than the end crosses midnight, e.g. `22:00-07:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Create a window from its start and end times
+    pub fn new(start: NaiveTime, end: NaiveTime) -> Self {
+        Self { start, end }
+    }
+
+    /// Check if the time falls inside the window
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Check if the current local time falls inside the window
+    pub fn is_active(&self) -> bool {
+        self.contains(Local::now().time())
+    }
+}
+
+impl FromStr for QuietHours {
+    type Err = String;
+
+    /// Parse a window written as `HH:MM-HH:MM`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-')
+            .ok_or_else(|| format!("Invalid quiet hours '{}': expected HH:MM-HH:MM", s))?;
+        let parse_time = |value: &str| NaiveTime::parse_from_str(value.trim(), "%H:%M")
+            .map_err(|_| format!("Invalid time '{}' in quiet hours '{}': expected HH:MM", value, s));
+
+        Ok(Self::new(parse_time(start)?, parse_time(end)?))
+    }
+}
@@ -0,0 +1,112 @@
+//! UUIDs of records already emitted, persisted across restarts.
+//!
+//! The in-memory deduplication starts over with every run, so
+//! replaying a backlog after a restart would emit everything again.
+//! Record UUIDs are never reused, unlike ROWIDs, so remembering the
+//! recent ones on disk makes a replay idempotent for the sinks.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use crate::error::BlurtError;
+
+/// Default number of UUIDs remembered
+pub const DEFAULT_SEEN_CAPACITY: usize = 10_000;
+
+/// The most recently seen record UUIDs, saved to a file as a JSON array
+///
+/// Once full the least recently seen UUID is forgotten for each new
+/// one, so the file stays small.
+#[derive(Debug)]
+pub struct SeenUuids {
+    path: PathBuf,
+    capacity: usize,
+    /// Oldest first
+    order: VecDeque<String>,
+    uuids: HashSet<String>,
+    /// Whether there are changes that weren't saved yet
+    dirty: bool,
+}
+
+impl SeenUuids {
+    /// Load the UUIDs saved at `path`, starting empty when there's no file yet
+    pub fn load(path: impl Into<PathBuf>, capacity: usize) -> Result<Self, BlurtError> {
+        let path = path.into();
+        let saved: Vec<String> = match std::fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| BlurtError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut seen = Self {
+            path,
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            uuids: HashSet::with_capacity(capacity),
+            dirty: false,
+        };
+        for uuid in saved {
+            seen.insert(uuid);
+        }
+        seen.dirty = false;
+        Ok(seen)
+    }
+
+    /// Get the path the UUIDs are saved to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get how many UUIDs are remembered
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Check if no UUIDs are remembered
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Check if the UUID was already seen
+    pub fn contains(&self, uuid: &str) -> bool {
+        self.uuids.contains(uuid)
+    }
+
+    /// Remember a UUID as the most recently seen, forgetting the oldest when full
+    pub fn insert(&mut self, uuid: String) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.uuids.contains(&uuid) {
+            self.order.retain(|seen| *seen != uuid);
+        } else {
+            if self.order.len() == self.capacity
+                && let Some(oldest) = self.order.pop_front() {
+                    self.uuids.remove(&oldest);
+                }
+            self.uuids.insert(uuid.clone());
+        }
+        self.order.push_back(uuid);
+        self.dirty = true;
+    }
+
+    /// Write the UUIDs to the file if they changed since it was loaded or last saved
+    ///
+    /// The file is replaced atomically so it's never left half
+    /// written if the daemon is killed mid-save.
+    pub fn save(&mut self) -> Result<(), BlurtError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let json = serde_json::to_vec(&self.order)
+            .map_err(|e| BlurtError::Io(std::io::Error::other(e)))?;
+        let mut temp_path = self.path.as_os_str().to_os_string();
+        temp_path.push(".tmp");
+        std::fs::write(&temp_path, json)?;
+        std::fs::rename(&temp_path, &self.path)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
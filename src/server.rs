@@ -0,0 +1,120 @@
+//! Minimal HTTP server exposing the daemon's metrics and health.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+use crate::error::BlurtError;
+use crate::metrics::Metrics;
+use crate::recent::RecentNotifications;
+
+/// Largest request head we're willing to read
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Serve the daemon's endpoints on the listener until the task is dropped
+///
+/// - `GET /metrics` renders the metrics for Prometheus
+/// - `GET /health` answers 200 while the last successful poll was
+///   less than `stale_after` ago and 503 otherwise, with the details
+///   as JSON
+/// - `GET /ready` answers 200 once a poll has succeeded and 503 before
+/// - `GET /recent` lists the last notifications dispatched as JSON,
+///   oldest first
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>, recent: Arc<RecentNotifications>, stale_after: Duration) -> Result<(), BlurtError> {
+    info!(addr = %listener.local_addr()?, "Serving metrics");
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let metrics = metrics.clone();
+        let recent = recent.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &metrics, &recent, stale_after).await {
+                debug!(%peer, error = %e, "Failed to handle metrics request");
+            }
+        });
+    }
+}
+
+/// Read a single request and write the response
+async fn handle_connection(mut stream: TcpStream, metrics: &Metrics, recent: &RecentNotifications, stale_after: Duration) -> Result<(), BlurtError> {
+    let Some(request) = read_request(&mut stream).await? else {
+        return respond(&mut stream, "400 Bad Request", "text/plain", "Bad Request\n").await;
+    };
+
+    match request.path.as_str() {
+        "/metrics" => respond(&mut stream, "200 OK", "text/plain; version=0.0.4", &metrics.render()).await,
+        "/health" => {
+            let health = metrics.health(stale_after);
+            let status = if health.healthy { "200 OK" } else { "503 Service Unavailable" };
+            let body = serde_json::to_string(&health).map_err(std::io::Error::from)?;
+            respond(&mut stream, status, "application/json", &body).await
+        }
+        "/ready" if metrics.is_ready() => respond(&mut stream, "200 OK", "text/plain", "Ready\n").await,
+        "/ready" => respond(&mut stream, "503 Service Unavailable", "text/plain", "Not Ready\n").await,
+        "/recent" => {
+            let body = serde_json::to_string(&recent.snapshot()).map_err(std::io::Error::from)?;
+            respond(&mut stream, "200 OK", "application/json", &body).await
+        }
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "Not Found\n").await,
+    }
+}
+
+/// A GET request's path and headers
+#[derive(Debug)]
+pub(crate) struct Request {
+    pub(crate) path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Get the value of a header, matching its name case insensitively
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Read the request head, `None` unless it's a complete GET request
+pub(crate) async fn read_request(stream: &mut TcpStream) -> Result<Option<Request>, BlurtError> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|w| w == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 || buffer.len() + read > MAX_REQUEST_SIZE {
+            warn!("Received an incomplete or oversized request");
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let head = String::from_utf8_lossy(&buffer);
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let path = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(path)) => path.to_string(),
+        _ => return Ok(None),
+    };
+    let headers = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Ok(Some(Request { path, headers }))
+}
+
+/// Write a complete response and close the connection
+pub(crate) async fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<(), BlurtError> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
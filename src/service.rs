@@ -0,0 +1,132 @@
+//! Running blurt at login as a launchd user agent.
+//!
+//! `install-service` writes a LaunchAgent plist that runs this
+//! binary with the daemon's flags, and `uninstall-service` removes it.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use plist::{Dictionary, Value};
+use tracing::{info, warn};
+
+/// Label the agent is installed under unless another one is chosen
+pub const DEFAULT_LABEL: &str = "com.alexkehayias.blurt";
+
+/// Path of the plist launchd loads the agent `label` from
+pub fn launch_agent_path(home: &Path, label: &str) -> PathBuf {
+    home.join("Library/LaunchAgents").join(format!("{}.plist", label))
+}
+
+/// Directory the agent's stdout and stderr are written to
+pub fn log_dir(home: &Path) -> PathBuf {
+    home.join("Library/Logs/blurt")
+}
+
+/// Build the LaunchAgent running `program` with the daemon's `args`
+///
+/// The agent starts at login and is restarted whenever it exits,
+/// except after a clean exit, e.g. when --max-runtime was reached.
+/// Notifications printed to stdout and logs on stderr go to files
+/// in `log_dir`.
+pub fn launch_agent(label: &str, program: &Path, args: &[String], log_dir: &Path) -> Value {
+    let arguments = std::iter::once(program.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(Value::String)
+        .collect();
+
+    let mut keep_alive = Dictionary::new();
+    keep_alive.insert("SuccessfulExit".to_string(), Value::Boolean(false));
+
+    let mut agent = Dictionary::new();
+    agent.insert("Label".to_string(), Value::String(label.to_string()));
+    agent.insert("ProgramArguments".to_string(), Value::Array(arguments));
+    agent.insert("RunAtLoad".to_string(), Value::Boolean(true));
+    agent.insert("KeepAlive".to_string(), Value::Dictionary(keep_alive));
+    agent.insert("ProcessType".to_string(), Value::String("Background".to_string()));
+    agent.insert("StandardOutPath".to_string(), path_value(&log_dir.join("blurt.out.log")));
+    agent.insert("StandardErrorPath".to_string(), path_value(&log_dir.join("blurt.err.log")));
+    Value::Dictionary(agent)
+}
+
+/// Write the LaunchAgent for this binary, loading it with launchctl if asked to
+///
+/// An agent already installed under the label is replaced, and
+/// unloaded first when loading the new one. Returns the plist's path.
+pub fn install(label: &str, args: &[String], load: bool) -> Result<PathBuf, String> {
+    let home = home_dir()?;
+    let program = std::env::current_exe()
+        .and_then(|program| program.canonicalize())
+        .map_err(|e| format!("Can't find the blurt binary to run: {}", e))?;
+
+    let path = launch_agent_path(&home, label);
+    let log_dir = log_dir(&home);
+    for dir in [path.parent().unwrap_or(&home), &log_dir] {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
+    }
+
+    let replacing = path.exists();
+    if replacing && load {
+        // Loading fails while the old agent is still loaded
+        let _ = launchctl("unload", &path);
+    }
+
+    launch_agent(label, &program, args, &log_dir).to_file_xml(&path)
+        .map_err(|e| format!("Can't write {}: {}", path.display(), e))?;
+    info!(path = %path.display(), replacing, "Installed launch agent");
+
+    if load {
+        launchctl("load", &path)?;
+        info!(label, "Loaded launch agent");
+    }
+
+    Ok(path)
+}
+
+/// Unload the LaunchAgent installed under the label and remove its plist
+pub fn uninstall(label: &str) -> Result<PathBuf, String> {
+    let path = launch_agent_path(&home_dir()?, label);
+    if !path.exists() {
+        return Err(format!("No launch agent is installed at {}", path.display()));
+    }
+
+    // It's fine if the agent was never loaded
+    if let Err(e) = launchctl("unload", &path) {
+        warn!(error = %e, "Failed to unload launch agent");
+    }
+
+    std::fs::remove_file(&path)
+        .map_err(|e| format!("Can't remove {}: {}", path.display(), e))?;
+    info!(path = %path.display(), "Uninstalled launch agent");
+    Ok(path)
+}
+
+/// Run `launchctl <subcommand> -w <path>`
+fn launchctl(subcommand: &str, path: &Path) -> Result<(), String> {
+    let output = Command::new("launchctl")
+        .arg(subcommand)
+        .arg("-w")
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Can't run launchctl: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "launchctl {} exited with {}: {}",
+            subcommand,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+fn home_dir() -> Result<PathBuf, String> {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .map_err(|_| "Can't find the LaunchAgents directory because HOME is not set".to_string())
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().into_owned())
+}
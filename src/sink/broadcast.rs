@@ -0,0 +1,123 @@
+//! Shared plumbing of the sinks that stream notifications to clients.
+
+use std::future::Future;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+use crate::database::Notification;
+use crate::recent::RecentNotifications;
+
+/// Number of events a client can fall behind before it misses some
+pub(crate) const CLIENT_BUFFER: usize = 64;
+
+/// A dispatched notification along with its id in the recent history
+#[derive(Debug, Clone)]
+pub(crate) struct Event {
+    pub(crate) id: u64,
+    pub(crate) notification: Notification,
+}
+
+/// Which kept notifications a new client receives before live ones
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Replay {
+    /// The ones after this id, or all that are kept when the id is
+    /// newer than any, i.e. it's from before the daemon restarted
+    After(u64),
+    /// Up to this many of the most recent
+    Last(usize),
+}
+
+/// Shares dispatched notifications with every subscribed client
+///
+/// Catching up uses the daemon's history of recent notifications,
+/// whose ids the events carry. The daemon pushes each notification
+/// to the history right before dispatching it, so the ones sent here
+/// are always the last pushed.
+pub(crate) struct Broadcaster {
+    sender: broadcast::Sender<Arc<Event>>,
+    recent: Arc<RecentNotifications>,
+}
+
+impl Broadcaster {
+    pub(crate) fn new(recent: Arc<RecentNotifications>) -> Self {
+        let (sender, _) = broadcast::channel(CLIENT_BUFFER);
+        Self { sender, recent }
+    }
+
+    /// Send notifications the daemon just dispatched, in order
+    pub(crate) fn send(&self, notifications: &[Notification]) {
+        let first_id = (self.recent.last_id() + 1).saturating_sub(notifications.len() as u64);
+        for (id, notification) in (first_id..).zip(notifications) {
+            // Sending only fails when no clients are subscribed
+            let _ = self.sender.send(Arc::new(Event { id, notification: notification.clone() }));
+        }
+    }
+
+    /// Subscribe to live events and get the kept ones to replay first, oldest first
+    pub(crate) fn subscribe(&self, replay: Replay) -> (Subscription, Vec<Event>) {
+        // Subscribing first means nothing is missed between the two,
+        // events both replayed and received live are skipped instead
+        let receiver = self.sender.subscribe();
+        let mut missed: Vec<Event> = match replay {
+            Replay::After(id) if id <= self.recent.last_id() => self.recent.since(id),
+            _ => self.recent.since(0),
+        }
+        .into_iter()
+        .map(|(id, notification)| Event { id, notification })
+        .collect();
+        if let Replay::Last(count) = replay {
+            missed.drain(..missed.len().saturating_sub(count));
+        }
+
+        let last_id = missed.last().map_or(0, |event| event.id);
+        (Subscription { receiver, last_id }, missed)
+    }
+}
+
+/// A client's feed of live events
+pub(crate) struct Subscription {
+    receiver: broadcast::Receiver<Arc<Event>>,
+    /// Id of the last event the client received
+    last_id: u64,
+}
+
+impl Subscription {
+    /// Wait for the next event, `None` once the sink is gone
+    ///
+    /// A client that fell too far behind skips the events it missed
+    /// rather than holding up the daemon.
+    pub(crate) async fn recv(&mut self) -> Option<Arc<Event>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if event.id <= self.last_id => {}
+                Ok(event) => {
+                    self.last_id = event.id;
+                    return Some(event);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "Client fell behind, skipped notifications");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Accept connections until the sink is dropped, serving each client in its own task
+pub(crate) async fn accept_clients<F, Fut>(listener: TcpListener, protocol: &'static str, serve: F)
+where
+    F: Fn(TcpStream) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                debug!(%addr, protocol, "Client connected");
+                tokio::spawn(serve(stream));
+            }
+            Err(e) => warn!(protocol, error = %e, "Failed to accept connection"),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+//! Sink that feeds the notification stream.
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Sends each notification to the receiving end of a stream
+pub(crate) struct ChannelSink {
+    sender: mpsc::Sender<Result<Notification, BlurtError>>,
+}
+
+impl ChannelSink {
+    pub(crate) fn new(sender: mpsc::Sender<Result<Notification, BlurtError>>) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for ChannelSink {
+    fn name(&self) -> &str {
+        "stream"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        // The receiver is only gone when the stream was dropped
+        let _ = self.sender.send(Ok(notification.clone())).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Sink that posts notifications to a Discord channel.
+
+use async_trait::async_trait;
+use reqwest::{Client, Response, StatusCode};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::warn;
+use crate::database::{truncate, Notification};
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Longest embed title Discord accepts, in characters
+const MAX_TITLE_LENGTH: usize = 256;
+
+/// Longest embed description Discord accepts, in characters
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+/// Longest embed footer Discord accepts, in characters
+const MAX_FOOTER_LENGTH: usize = 2048;
+
+/// How many times a rate limited message is retried
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// Wait used when a rate limited response has no usable `Retry-After`
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Longest `Retry-After` honored so a bad header can't stall the daemon
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Posts each notification as an embed through a Discord webhook
+pub struct DiscordSink {
+    client: Client,
+    url: String,
+}
+
+impl DiscordSink {
+    /// Create a sink that posts to the webhook URL
+    pub fn new(url: String) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for DiscordSink {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let payload = format_payload(notification);
+
+        let mut retries = 0;
+        loop {
+            let response = self.client.post(&self.url)
+                .timeout(Duration::from_secs(10))
+                .json(&payload)
+                .send()
+                .await
+                .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+            if response.status() == StatusCode::TOO_MANY_REQUESTS && retries < MAX_RATE_LIMIT_RETRIES {
+                let retry_after = retry_after(&response);
+                warn!(?retry_after, "Rate limited by Discord, retrying");
+                sleep(retry_after).await;
+                retries += 1;
+                continue;
+            }
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(BlurtError::Sink(format!("Discord returned {}: {}", status, body)));
+            }
+
+            return Ok(());
+        }
+    }
+}
+
+/// Build the webhook payload with the notification as a single embed
+fn format_payload(notification: &Notification) -> serde_json::Value {
+    let app = notification.app_name.as_deref()
+        .or(notification.bundle_id.as_deref())
+        .unwrap_or("unknown");
+    let mut description = notification.body.clone();
+    if let Some(subtitle) = &notification.subtitle {
+        description = format!("**{}**\n{}", subtitle, description);
+    }
+
+    serde_json::json!({
+        "embeds": [{
+            "title": truncate(&notification.title, MAX_TITLE_LENGTH),
+            "description": truncate(&description, MAX_DESCRIPTION_LENGTH),
+            "footer": {
+                "text": truncate(app, MAX_FOOTER_LENGTH),
+            },
+        }],
+    })
+}
+
+/// Read how long to wait from the `Retry-After` header, in seconds
+fn retry_after(response: &Response) -> Duration {
+    response.headers()
+        .get("retry-after")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .unwrap_or(DEFAULT_RETRY_AFTER)
+        .min(MAX_RETRY_AFTER)
+}
@@ -0,0 +1,36 @@
+//! Sink that logs notifications instead of dispatching them.
+
+use async_trait::async_trait;
+use tracing::info;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Logs each notification and the sink it would have been sent to
+///
+/// Used to try out filters and sink configuration without sending
+/// anything. The wrapped sink is never called.
+pub struct DryRunSink {
+    sink: Box<dyn NotificationSink>,
+}
+
+impl DryRunSink {
+    /// Stand in for a sink without dispatching to it
+    pub fn new(sink: Box<dyn NotificationSink>) -> Self {
+        Self { sink }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for DryRunSink {
+    fn name(&self) -> &str {
+        "dry-run"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let json = serde_json::to_string(notification)
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+        info!(sink = self.sink.name(), notification = %json, "Dry run, not dispatching notification");
+        Ok(())
+    }
+}
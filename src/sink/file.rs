@@ -0,0 +1,102 @@
+//! Sink that appends notifications to a rotating log file.
+
+use async_trait::async_trait;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Default size in bytes a log file can grow to before it's rotated
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Number of rotated files kept next to the active log file
+const ROTATED_FILES: usize = 5;
+
+/// Appends each notification as a line of JSON to a file
+///
+/// When the file would grow past the size limit it's rotated to
+/// `<path>.1`, shifting older files up to `<path>.5`.
+pub struct FileSink {
+    path: PathBuf,
+    max_size: u64,
+    file: Mutex<LogFile>,
+}
+
+/// The currently open log file and how many bytes it holds
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+impl FileSink {
+    /// Open the log file for appending, creating missing directories
+    pub fn new(path: impl AsRef<Path>, max_size: u64) -> Result<Self, BlurtError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+
+        let file = open_append(&path)?;
+        Ok(Self {
+            path,
+            max_size,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Shift rotated files up by one and move the active file to `.1`
+    fn rotate(&self) -> Result<LogFile, BlurtError> {
+        for i in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, i);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, i + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+
+        open_append(&self.path)
+    }
+}
+
+#[async_trait]
+impl NotificationSink for FileSink {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let mut line = serde_json::to_string(notification)
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+        line.push('\n');
+
+        let mut log_file = self.file.lock().map_err(|e| BlurtError::Sink(e.to_string()))?;
+        if log_file.size > 0 && log_file.size + line.len() as u64 > self.max_size {
+            *log_file = self.rotate()?;
+        }
+
+        // Flush after every write so a crash doesn't lose recent entries
+        log_file.file.write_all(line.as_bytes())?;
+        log_file.file.flush()?;
+        log_file.size += line.len() as u64;
+
+        Ok(())
+    }
+}
+
+/// Open a file for appending and read its current size
+fn open_append(path: &Path) -> Result<LogFile, BlurtError> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok(LogFile { file, size })
+}
+
+/// Path of the nth rotated log file
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
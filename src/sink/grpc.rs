@@ -0,0 +1,153 @@
+//! Sink that streams notifications to gRPC clients.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt, stream};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status};
+use tracing::{debug, warn};
+use crate::database::{self, Notification, RecordMetadata};
+use crate::error::BlurtError;
+use crate::recent::RecentNotifications;
+use super::NotificationSink;
+use super::broadcast::{Broadcaster, Replay};
+
+/// Types generated from proto/blurt.proto
+#[allow(clippy::all, clippy::pedantic)]
+pub mod proto {
+    tonic::include_proto!("blurt.v1");
+}
+
+use proto::notifications_server::{Notifications, NotificationsServer};
+
+/// Streams each notification as a protobuf message to every subscribed client
+///
+/// Clients call `Subscribe` on the `blurt.v1.Notifications` service,
+/// asking for up to `backfill` of the daemon's recent notifications
+/// first if they want to. Each client is served by its own stream so
+/// a slow or disconnecting client never blocks the daemon, it skips
+/// the messages it fell too far behind on instead.
+pub struct GrpcSink {
+    broadcaster: Arc<Broadcaster>,
+    server: JoinHandle<()>,
+}
+
+impl GrpcSink {
+    /// Serve the Notifications service on the listener, backfilling from `recent`
+    pub fn new(listener: TcpListener, recent: Arc<RecentNotifications>) -> Self {
+        let broadcaster = Arc::new(Broadcaster::new(recent));
+        let service = NotificationsServer::new(Service { broadcaster: broadcaster.clone() });
+        let server = tokio::spawn(async move {
+            let result = tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await;
+            if let Err(e) = result {
+                warn!(error = %e, "gRPC server stopped");
+            }
+        });
+
+        Self { broadcaster, server }
+    }
+}
+
+impl Drop for GrpcSink {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[async_trait]
+impl NotificationSink for GrpcSink {
+    fn name(&self) -> &str {
+        "grpc"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        self.broadcaster.send(std::slice::from_ref(notification));
+        Ok(())
+    }
+
+    async fn handle_batch(&self, notifications: &[Notification]) -> Result<(), BlurtError> {
+        self.broadcaster.send(notifications);
+        Ok(())
+    }
+}
+
+/// Serves the clients' subscriptions from the broadcaster
+struct Service {
+    broadcaster: Arc<Broadcaster>,
+}
+
+type NotificationStream = Pin<Box<dyn Stream<Item = Result<proto::Notification, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Notifications for Service {
+    type SubscribeStream = NotificationStream;
+
+    async fn subscribe(&self, request: Request<proto::SubscribeRequest>) -> Result<Response<Self::SubscribeStream>, Status> {
+        let backfill = request.get_ref().backfill as usize;
+        let (subscription, missed) = self.broadcaster.subscribe(Replay::Last(backfill));
+        debug!(backfill, "gRPC client subscribed");
+
+        let missed: Vec<Result<proto::Notification, Status>> = missed.iter()
+            .map(|event| Ok((&event.notification).into()))
+            .collect();
+        let live = stream::unfold(subscription, |mut subscription| async move {
+            let event = subscription.recv().await?;
+            Some((Ok((&event.notification).into()), subscription))
+        });
+
+        Ok(Response::new(stream::iter(missed).chain(live).boxed()))
+    }
+}
+
+impl From<&Notification> for proto::Notification {
+    fn from(notification: &Notification) -> Self {
+        Self {
+            id: notification.id,
+            uuid: notification.uuid.clone(),
+            title: notification.title.clone(),
+            subtitle: notification.subtitle.clone(),
+            body: notification.body.clone(),
+            date: notification.date,
+            presented: notification.presented,
+            snoozed: notification.snoozed,
+            bundle_id: notification.bundle_id.clone(),
+            app_name: notification.app_name.clone(),
+            sound: notification.sound.clone(),
+            thread_id: notification.thread_id.clone(),
+            category: notification.category.clone(),
+            interruption_level: notification.interruption_level.as_ref().map(|level| level.as_str().to_string()),
+            attachments: notification.attachments.clone(),
+            actions: notification.actions.iter().map(proto::Action::from).collect(),
+            source: notification.source.clone(),
+            metadata: notification.metadata.as_ref().map(proto::RecordMetadata::from),
+            event: notification.event.map(|event| event.as_str().to_string()),
+        }
+    }
+}
+
+impl From<&RecordMetadata> for proto::RecordMetadata {
+    fn from(metadata: &RecordMetadata) -> Self {
+        Self {
+            rec_id: metadata.rec_id,
+            app_id: metadata.app_id,
+            presented: metadata.presented,
+            style: metadata.style,
+            delivered_date: metadata.delivered_date,
+        }
+    }
+}
+
+impl From<&database::Action> for proto::Action {
+    fn from(action: &database::Action) -> Self {
+        Self {
+            title: action.title.clone(),
+            identifier: action.identifier.clone(),
+        }
+    }
+}
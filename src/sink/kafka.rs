@@ -0,0 +1,69 @@
+//! Sink that produces notifications to a Kafka topic.
+
+use std::time::Duration;
+use async_trait::async_trait;
+use rdkafka::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+use tracing::warn;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// How long to wait for the brokers to acknowledge a notification
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Produces each notification as JSON to a Kafka topic
+///
+/// Messages are keyed by bundle identifier so an app's notifications
+/// land on the same partition and stay in order. A single producer
+/// is created up front and reused for every notification.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaSink {
+    /// Create a producer for the comma separated `host:port` brokers
+    pub fn new(brokers: &str, topic: String) -> Result<Self, BlurtError> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("client.id", "blurt")
+            .set("message.timeout.ms", DELIVERY_TIMEOUT.as_millis().to_string())
+            .create()
+            .map_err(|e| BlurtError::Sink(format!("failed to create Kafka producer for {}: {}", brokers, e)))?;
+
+        Ok(Self { producer, topic })
+    }
+}
+
+impl Drop for KafkaSink {
+    fn drop(&mut self) {
+        // Deliver anything still queued before shutting down
+        if let Err(e) = self.producer.flush(Timeout::After(DELIVERY_TIMEOUT)) {
+            warn!(error = %e, "Failed to flush Kafka producer");
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for KafkaSink {
+    fn name(&self) -> &str {
+        "kafka"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let key = notification.bundle_id.as_deref().unwrap_or("unknown");
+        let payload = serde_json::to_vec(notification)
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+        let record = FutureRecord::to(&self.topic)
+            .key(key)
+            .payload(&payload);
+
+        // Resolves once the brokers acknowledge the message or it times out
+        self.producer.send(record, Timeout::After(DELIVERY_TIMEOUT))
+            .await
+            .map(|_| ())
+            .map_err(|(e, _)| BlurtError::Sink(format!("failed to produce to Kafka topic {}: {}", self.topic, e)))
+    }
+}
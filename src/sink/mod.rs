@@ -0,0 +1,120 @@
+//! Sinks that parsed notifications are dispatched to.
+
+use std::time::Duration;
+use async_trait::async_trait;
+use crate::database::Notification;
+use crate::error::BlurtError;
+
+mod broadcast;
+mod channel;
+#[cfg(feature = "discord")]
+mod discord;
+mod dry_run;
+#[cfg(feature = "email")]
+mod email;
+mod file;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod native;
+#[cfg(feature = "nats")]
+mod nats;
+#[cfg(feature = "ntfy")]
+mod ntfy;
+#[cfg(feature = "pushover")]
+mod pushover;
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "slack")]
+mod slack;
+mod sse;
+mod statsd;
+mod stdout;
+#[cfg(feature = "syslog")]
+mod syslog;
+#[cfg(feature = "telegram")]
+mod telegram;
+#[cfg(unix)]
+mod unix_socket;
+#[cfg(feature = "webhook")]
+mod webhook;
+#[cfg(feature = "websocket")]
+mod websocket;
+
+pub(crate) use channel::ChannelSink;
+#[cfg(feature = "discord")]
+pub use discord::DiscordSink;
+pub use dry_run::DryRunSink;
+#[cfg(feature = "email")]
+pub use email::EmailSink;
+pub use file::{FileSink, DEFAULT_MAX_SIZE};
+#[cfg(feature = "grpc")]
+pub use grpc::{proto, GrpcSink};
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaSink;
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttSink, DEFAULT_MQTT_PORT};
+pub use native::{NativeNotifySink, OSASCRIPT_BUNDLE_ID};
+#[cfg(feature = "nats")]
+pub use nats::NatsSink;
+#[cfg(feature = "ntfy")]
+pub use ntfy::NtfySink;
+#[cfg(feature = "pushover")]
+pub use pushover::PushoverSink;
+#[cfg(feature = "redis")]
+pub use self::redis::RedisSink;
+#[cfg(feature = "slack")]
+pub use slack::SlackSink;
+pub use sse::SseSink;
+pub use statsd::{StatsdSink, DEFAULT_STATSD_PREFIX};
+pub use stdout::StdoutSink;
+#[cfg(feature = "syslog")]
+pub use self::syslog::SyslogSink;
+#[cfg(feature = "telegram")]
+pub use telegram::TelegramSink;
+#[cfg(unix)]
+pub use unix_socket::UnixSocketSink;
+#[cfg(feature = "webhook")]
+pub use webhook::WebhookSink;
+#[cfg(feature = "websocket")]
+pub use websocket::WebSocketSink;
+
+/// A destination for notifications found by the daemon
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    /// Short name of the destination used in logs
+    fn name(&self) -> &str {
+        "custom"
+    }
+
+    /// Deliver a single notification
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError>;
+
+    /// Observe how long a check of the databases took
+    ///
+    /// Called after every check, sinks reporting metrics override it.
+    fn record_poll(&self, _latency: Duration) {}
+
+    /// Deliver the notifications found in a single check
+    ///
+    /// Only called when batching is enabled. Sinks that can send
+    /// several notifications at once override it, by default each one
+    /// is handled in turn and the first failure is returned once all
+    /// of them were attempted.
+    async fn handle_batch(&self, notifications: &[Notification]) -> Result<(), BlurtError> {
+        let mut first_error = None;
+        for notification in notifications {
+            if let Err(e) = self.handle(notification).await {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Sink that publishes notifications to an MQTT broker.
+
+use async_trait::async_trait;
+use rumqttc::{AsyncClient, EventLoop, MqttOptions, QoS};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::warn;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Default MQTT broker port
+pub const DEFAULT_MQTT_PORT: u16 = 1883;
+
+/// Publishes each notification as JSON to an MQTT topic with QoS 1
+///
+/// `{bundle_id}` in the topic is replaced with the notification's app.
+/// A single connection is kept for the lifetime of the sink. The
+/// connection is driven by a background task that reconnects
+/// automatically whenever it drops.
+pub struct MqttSink {
+    client: AsyncClient,
+    topic: String,
+    event_loop: JoinHandle<()>,
+}
+
+impl MqttSink {
+    /// Connect to the broker at the `mqtt://host:port` URL
+    pub fn new(url: &str, topic: String) -> Result<Self, BlurtError> {
+        let (host, port) = parse_mqtt_url(url)?;
+        let mut options = MqttOptions::new(format!("blurt-{}", std::process::id()), host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+
+        let (client, event_loop) = AsyncClient::new(options, 64);
+        Ok(Self {
+            client,
+            topic,
+            event_loop: tokio::spawn(drive_event_loop(event_loop)),
+        })
+    }
+}
+
+impl Drop for MqttSink {
+    fn drop(&mut self) {
+        self.event_loop.abort();
+    }
+}
+
+#[async_trait]
+impl NotificationSink for MqttSink {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let topic = self.topic.replace("{bundle_id}", notification.bundle_id.as_deref().unwrap_or("unknown"));
+        let payload = serde_json::to_vec(notification)
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+        self.client.publish(topic, QoS::AtLeastOnce, false, payload)
+            .await
+            .map_err(|e| BlurtError::Sink(e.to_string()))
+    }
+}
+
+/// Poll the connection forever, polling again after an error reconnects
+async fn drive_event_loop(mut event_loop: EventLoop) {
+    loop {
+        if let Err(e) = event_loop.poll().await {
+            warn!(error = %e, "MQTT connection failed, reconnecting");
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// Split an `mqtt://host:port` URL into its host and port
+fn parse_mqtt_url(url: &str) -> Result<(String, u16), BlurtError> {
+    let address = url.strip_prefix("mqtt://").unwrap_or(url).trim_end_matches('/');
+    match address.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port.parse()
+                .map_err(|_| BlurtError::Sink(format!("Invalid port in MQTT URL '{}'", url)))?;
+            Ok((host.to_string(), port))
+        }
+        None if !address.is_empty() => Ok((address.to_string(), DEFAULT_MQTT_PORT)),
+        None => Err(BlurtError::Sink(format!("Invalid MQTT URL '{}'", url))),
+    }
+}
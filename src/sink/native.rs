@@ -0,0 +1,65 @@
+//! Sink that re-posts notifications as native macOS banners.
+
+use async_trait::async_trait;
+use tokio::process::Command;
+use tracing::debug;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Bundle id that banners posted through `osascript` are attributed to
+///
+/// Notifications from it are the ones this sink posted so they're
+/// never re-posted, otherwise every banner would trigger another.
+pub const OSASCRIPT_BUNDLE_ID: &str = "com.apple.ScriptEditor2";
+
+/// Posts each notification as a native banner using `osascript`
+pub struct NativeNotifySink;
+
+#[async_trait]
+impl NotificationSink for NativeNotifySink {
+    fn name(&self) -> &str {
+        "notify"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        if notification.bundle_id.as_deref() == Some(OSASCRIPT_BUNDLE_ID) {
+            debug!(id = notification.id, "Skipping notification posted by blurt");
+            return Ok(());
+        }
+
+        let output = Command::new("osascript")
+            .arg("-e")
+            .arg(display_notification_script(notification))
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(BlurtError::Sink(format!(
+                "osascript exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Build the AppleScript that displays the notification banner
+fn display_notification_script(notification: &Notification) -> String {
+    let mut script = format!(
+        "display notification \"{}\" with title \"{}\"",
+        escape_applescript(&notification.body),
+        escape_applescript(&notification.title)
+    );
+    if let Some(subtitle) = &notification.subtitle {
+        script.push_str(&format!(" subtitle \"{}\"", escape_applescript(subtitle)));
+    }
+    script
+}
+
+/// Escape a value for use inside an AppleScript string literal
+fn escape_applescript(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
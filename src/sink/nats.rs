@@ -0,0 +1,66 @@
+//! Sink that publishes notifications to NATS.
+
+use async_nats::{Client, ConnectOptions, Event};
+use async_trait::async_trait;
+use tracing::{info, warn};
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Publishes each notification as JSON to a NATS subject
+///
+/// `{bundle_id}` in the subject is replaced with the notification's
+/// app. The client reconnects on its own whenever the connection
+/// drops, buffering publishes in the meantime.
+pub struct NatsSink {
+    client: Client,
+    subject: String,
+}
+
+impl NatsSink {
+    /// Connect to the server at the `nats://host:port` URL
+    pub async fn connect(url: &str, subject: String) -> Result<Self, BlurtError> {
+        let client = ConnectOptions::new()
+            .name("blurt")
+            .event_callback(|event| async move {
+                match event {
+                    Event::Connected => info!("Connected to NATS"),
+                    Event::Disconnected => warn!("NATS connection lost, reconnecting"),
+                    event => warn!(%event, "NATS client event"),
+                }
+            })
+            .connect(url)
+            .await
+            .map_err(|e| BlurtError::Sink(format!("failed to connect to NATS at {}: {}", url, e)))?;
+
+        Ok(Self { client, subject })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for NatsSink {
+    fn name(&self) -> &str {
+        "nats"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let bundle_id = notification.bundle_id.as_deref().unwrap_or("unknown");
+        let subject = self.subject.replace("{bundle_id}", &subject_token(bundle_id));
+        let payload = serde_json::to_vec(notification)
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+        self.client.publish(subject, payload.into())
+            .await
+            .map_err(|e| BlurtError::Sink(e.to_string()))
+    }
+}
+
+/// Replace the characters NATS doesn't allow in a subject
+///
+/// Dots are kept so `com.apple.MobileSMS` can still be matched
+/// token by token.
+fn subject_token(value: &str) -> String {
+    value.chars()
+        .map(|c| if c.is_whitespace() || c == '*' || c == '>' { '_' } else { c })
+        .collect()
+}
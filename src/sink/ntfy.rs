@@ -0,0 +1,73 @@
+//! Sink that pushes notifications through ntfy.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use reqwest::header::HeaderValue;
+use std::time::Duration;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Publishes each notification to an ntfy topic
+///
+/// The title and app are sent as the `Title` and `Tags` headers and
+/// the body as the message. Works with ntfy.sh and self-hosted servers.
+pub struct NtfySink {
+    client: Client,
+    url: String,
+    token: Option<String>,
+}
+
+impl NtfySink {
+    /// Publish to the topic URL, e.g. `https://ntfy.sh/my-topic`, with an optional access token
+    pub fn new(url: String, token: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            token,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for NtfySink {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let mut request = self.client.post(&self.url)
+            .timeout(Duration::from_secs(10))
+            .header("Title", header_value(&notification.title)?)
+            .body(notification.body.clone());
+        if let Some(bundle_id) = &notification.bundle_id {
+            request = request.header("Tags", header_value(bundle_id)?);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send()
+            .await
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(BlurtError::Sink(format!("ntfy returned {}: {}", status, body)));
+        }
+
+        Ok(())
+    }
+}
+
+/// Build a header value, replacing the control characters headers can't hold
+///
+/// ntfy reads headers as UTF-8 so other characters are sent as is.
+fn header_value(value: &str) -> Result<HeaderValue, BlurtError> {
+    let value: String = value.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    HeaderValue::from_bytes(value.as_bytes())
+        .map_err(|e| BlurtError::Sink(e.to_string()))
+}
@@ -0,0 +1,127 @@
+//! Sink that sends notifications through Pushover.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::warn;
+use crate::database::{truncate, Notification};
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Pushover's message API
+const API_URL: &str = "https://api.pushover.net/1/messages.json";
+
+/// Longest title Pushover accepts, in characters
+const MAX_TITLE_LENGTH: usize = 250;
+
+/// Longest message Pushover accepts, in characters
+const MAX_MESSAGE_LENGTH: usize = 1024;
+
+/// How many times a message is retried after a transient failure
+const MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled on each one
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Seconds between repeats of an emergency priority alert until acknowledged
+const EMERGENCY_RETRY: u32 = 60;
+
+/// Seconds after which an unacknowledged emergency priority alert stops repeating
+const EMERGENCY_EXPIRE: u32 = 3600;
+
+/// Sends each notification as a Pushover message
+pub struct PushoverSink {
+    client: Client,
+    token: String,
+    user: String,
+    priority: i8,
+}
+
+impl PushoverSink {
+    /// Create a sink that sends with the app token to the user or group key
+    ///
+    /// `priority` ranges from -2 (no alert) to 2 (emergency, repeated
+    /// until acknowledged).
+    pub fn new(token: String, user: String, priority: i8) -> Self {
+        Self {
+            client: Client::new(),
+            token,
+            user,
+            priority,
+        }
+    }
+
+    /// Send the message once
+    ///
+    /// Returns whether a failure is worth retrying along with the error.
+    async fn send(&self, form: &[(&str, String)]) -> Result<(), (bool, BlurtError)> {
+        let response = self.client.post(API_URL)
+            .timeout(Duration::from_secs(10))
+            .form(form)
+            .send()
+            .await
+            .map_err(|e| (true, BlurtError::Sink(e.to_string())))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            // Pushover asks clients not to retry 4xx responses, they won't succeed
+            let transient = status.is_server_error();
+            return Err((transient, BlurtError::Sink(format!("Pushover returned {}: {}", status, body))));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NotificationSink for PushoverSink {
+    fn name(&self) -> &str {
+        "pushover"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let app = notification.app_name.as_deref()
+            .or(notification.bundle_id.as_deref())
+            .unwrap_or("unknown");
+        let title = if notification.title.is_empty() {
+            app.to_string()
+        } else {
+            format!("{}: {}", app, notification.title)
+        };
+        // Pushover rejects empty messages
+        let message = if notification.body.is_empty() { "(no body)" } else { notification.body.as_str() };
+
+        let mut form = vec![
+            ("token", self.token.clone()),
+            ("user", self.user.clone()),
+            ("title", truncate(&title, MAX_TITLE_LENGTH)),
+            ("message", truncate(message, MAX_MESSAGE_LENGTH)),
+            ("priority", self.priority.to_string()),
+        ];
+        // Pushover falls back to when it received the message
+        if let Some(delivered_at) = notification.delivered_at() {
+            form.push(("timestamp", delivered_at.timestamp().to_string()));
+        }
+        if self.priority == 2 {
+            form.push(("retry", EMERGENCY_RETRY.to_string()));
+            form.push(("expire", EMERGENCY_EXPIRE.to_string()));
+        }
+
+        let mut backoff = RETRY_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            match self.send(&form).await {
+                Ok(()) => return Ok(()),
+                Err((true, e)) if attempt < MAX_RETRIES => {
+                    attempt += 1;
+                    warn!(attempt, ?backoff, error = %e, "Pushover request failed, retrying");
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err((_, e)) => return Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Sink that publishes notifications to Redis.
+
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use crate::cli::RedisMode;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Publishes each notification as JSON to a Redis channel or stream
+///
+/// `{bundle_id}` in the channel is replaced with the notification's
+/// app. In `pub` mode notifications are fanned out to whoever is
+/// subscribed at the time, in `stream` mode they're appended to a
+/// stream under the `notification` field and kept for consumers
+/// that read later. The connection is opened once and re-established
+/// whenever it drops.
+pub struct RedisSink {
+    connection: ConnectionManager,
+    channel: String,
+    mode: RedisMode,
+}
+
+impl RedisSink {
+    /// Connect to the server at the `redis://host:port` URL
+    pub async fn connect(url: &str, channel: String, mode: RedisMode) -> Result<Self, BlurtError> {
+        let client = redis::Client::open(url)
+            .map_err(|e| BlurtError::Sink(format!("invalid Redis URL {}: {}", url, e)))?;
+        let connection = client.get_connection_manager()
+            .await
+            .map_err(|e| BlurtError::Sink(format!("failed to connect to Redis at {}: {}", url, e)))?;
+
+        Ok(Self { connection, channel, mode })
+    }
+}
+
+#[async_trait]
+impl NotificationSink for RedisSink {
+    fn name(&self) -> &str {
+        "redis"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let bundle_id = notification.bundle_id.as_deref().unwrap_or("unknown");
+        let channel = self.channel.replace("{bundle_id}", bundle_id);
+        let payload = serde_json::to_string(notification)
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+        let command = match self.mode {
+            RedisMode::Pub => redis::cmd("PUBLISH").arg(&channel).arg(payload).to_owned(),
+            RedisMode::Stream => redis::cmd("XADD").arg(&channel).arg("*").arg("notification").arg(payload).to_owned(),
+        };
+
+        // The manager is a cheap handle onto the shared connection
+        let mut connection = self.connection.clone();
+        command.query_async::<()>(&mut connection)
+            .await
+            .map_err(|e| BlurtError::Sink(format!("failed to publish to Redis {}: {}", channel, e)))
+    }
+}
@@ -0,0 +1,133 @@
+//! Sink that posts notifications to a Slack channel.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use std::time::Duration;
+use crate::database::{truncate, Notification};
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Longest header block text Slack accepts, in characters
+const MAX_HEADER_LENGTH: usize = 150;
+
+/// Longest section block text Slack accepts, in characters
+const MAX_SECTION_LENGTH: usize = 3000;
+
+/// Longest context element text Slack accepts, in characters
+const MAX_CONTEXT_LENGTH: usize = 2000;
+
+/// Posts each notification as a Block Kit message through a Slack incoming webhook
+pub struct SlackSink {
+    client: Client,
+    url: String,
+    channel: Option<String>,
+}
+
+impl SlackSink {
+    /// Create a sink that posts to the webhook URL
+    ///
+    /// `channel` overrides the webhook's default channel where the
+    /// webhook allows it.
+    pub fn new(url: String, channel: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            channel,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for SlackSink {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let response = self.client.post(&self.url)
+            .timeout(Duration::from_secs(10))
+            .json(&format_payload(notification, self.channel.as_deref()))
+            .send()
+            .await
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(BlurtError::Sink(format!("Slack returned {}: {}", status, body)));
+        }
+
+        Ok(())
+    }
+}
+
+/// Build the message with the title as header, the body as a section and the app and time as context
+fn format_payload(notification: &Notification, channel: Option<&str>) -> serde_json::Value {
+    let app = notification.app_name.as_deref()
+        .or(notification.bundle_id.as_deref())
+        .unwrap_or("unknown");
+    // Slack renders the date in the reader's timezone, falling back to UTC
+    let date = notification.delivered_at()
+        .map(|date| format!(" · <!date^{}^{{date_short_pretty}} {{time}}|{}>", date.timestamp(), date.format("%Y-%m-%d %H:%M UTC")))
+        .unwrap_or_default();
+    let context = format!("{}{}", escape_truncated(app, MAX_CONTEXT_LENGTH.saturating_sub(date.chars().count())), date);
+
+    let mut section = String::new();
+    if let Some(subtitle) = &notification.subtitle {
+        section = format!("*{}*\n", escape_truncated(subtitle, MAX_SECTION_LENGTH.saturating_sub(3)));
+    }
+    section.push_str(&escape_truncated(&notification.body, MAX_SECTION_LENGTH.saturating_sub(section.chars().count())));
+
+    let mut blocks = Vec::new();
+    if !notification.title.is_empty() {
+        blocks.push(serde_json::json!({
+            "type": "header",
+            "text": {"type": "plain_text", "text": truncate(&notification.title, MAX_HEADER_LENGTH)},
+        }));
+    }
+    // Slack rejects sections with empty text
+    if !section.is_empty() {
+        blocks.push(serde_json::json!({
+            "type": "section",
+            "text": {"type": "mrkdwn", "text": section},
+        }));
+    }
+    blocks.push(serde_json::json!({
+        "type": "context",
+        "elements": [{"type": "mrkdwn", "text": context}],
+    }));
+
+    let mut payload = serde_json::json!({
+        // Shown in push notifications and clients that can't render blocks
+        "text": truncate(&notification.summary(), MAX_SECTION_LENGTH),
+        "blocks": blocks,
+    });
+    if let Some(channel) = channel {
+        payload["channel"] = channel.into();
+    }
+    payload
+}
+
+/// Escape text for mrkdwn, shortening it first so the escaped text fits in `max_chars`
+///
+/// Cutting the raw text rather than the escaped one never leaves
+/// half an entity behind.
+fn escape_truncated(value: &str, max_chars: usize) -> String {
+    let mut length = 0;
+    let fitting = value.chars()
+        .take_while(|c| {
+            length += match c {
+                '&' => "&amp;".len(),
+                '<' | '>' => "&lt;".len(),
+                _ => 1,
+            };
+            length <= max_chars
+        })
+        .count();
+    escape_mrkdwn(&truncate(value, fitting))
+}
+
+/// Escape the characters Slack's mrkdwn treats specially
+fn escape_mrkdwn(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,116 @@
+//! Sink that streams notifications to clients as Server-Sent Events.
+
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::debug;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use crate::recent::RecentNotifications;
+use crate::server::{read_request, respond};
+use super::NotificationSink;
+use super::broadcast::{accept_clients, Broadcaster, Event, Replay};
+
+/// How often an idle connection gets a comment, so proxies keep it
+/// open and disconnected clients are noticed
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Path the event stream is served on
+const EVENTS_PATH: &str = "/events";
+
+/// Streams each notification as an event to every client of `GET /events`
+///
+/// Each event's data is the notification as JSON and its id counts
+/// up from 1 with every notification dispatched. A client reconnecting
+/// with a `Last-Event-ID` header first receives what it missed since
+/// that event, as long as it's still in the daemon's recent history.
+/// Each client is served by its own task so a slow client never
+/// blocks the daemon, it skips the events it fell too far behind on
+/// instead.
+pub struct SseSink {
+    broadcaster: Arc<Broadcaster>,
+    server: JoinHandle<()>,
+}
+
+impl SseSink {
+    /// Accept event stream clients on the listener, catching them up from `recent`
+    pub fn new(listener: TcpListener, recent: Arc<RecentNotifications>) -> Self {
+        let broadcaster = Arc::new(Broadcaster::new(recent));
+        let clients = broadcaster.clone();
+        let server = tokio::spawn(accept_clients(listener, "sse", move |stream| serve_client(stream, clients.clone())));
+        Self { broadcaster, server }
+    }
+}
+
+impl Drop for SseSink {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[async_trait]
+impl NotificationSink for SseSink {
+    fn name(&self) -> &str {
+        "sse"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        self.broadcaster.send(std::slice::from_ref(notification));
+        Ok(())
+    }
+
+    async fn handle_batch(&self, notifications: &[Notification]) -> Result<(), BlurtError> {
+        self.broadcaster.send(notifications);
+        Ok(())
+    }
+}
+
+/// Stream events to a client until it disconnects
+async fn serve_client(mut stream: TcpStream, broadcaster: Arc<Broadcaster>) {
+    if let Err(e) = stream_events(&mut stream, &broadcaster).await {
+        debug!(error = %e, "SSE client disconnected");
+    }
+}
+
+async fn stream_events(stream: &mut TcpStream, broadcaster: &Broadcaster) -> Result<(), BlurtError> {
+    let Some(request) = read_request(stream).await? else {
+        return respond(stream, "400 Bad Request", "text/plain", "Bad Request\n").await;
+    };
+    if request.path != EVENTS_PATH {
+        return respond(stream, "404 Not Found", "text/plain", "Not Found\n").await;
+    }
+
+    // Without an id there's nothing the client missed
+    let replay = match request.header("Last-Event-ID").and_then(|id| id.parse().ok()) {
+        Some(last_event_id) => Replay::After(last_event_id),
+        None => Replay::Last(0),
+    };
+    let (mut events, missed) = broadcaster.subscribe(replay);
+
+    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n").await?;
+    for event in &missed {
+        stream.write_all(format_event(event)?.as_bytes()).await?;
+    }
+
+    let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+    keep_alive.reset();
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => stream.write_all(format_event(&event)?.as_bytes()).await?,
+                None => return Ok(()),
+            },
+            _ = keep_alive.tick() => stream.write_all(b": keep-alive\n\n").await?,
+        }
+    }
+}
+
+/// Format an event in the event stream format
+fn format_event(event: &Event) -> Result<String, BlurtError> {
+    let json = serde_json::to_string(&event.notification)
+        .map_err(|e| BlurtError::Sink(e.to_string()))?;
+    Ok(format!("id: {}\ndata: {}\n\n", event.id, json))
+}
@@ -0,0 +1,78 @@
+//! Sink that reports notification counts and poll latency to StatsD.
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+use async_trait::async_trait;
+use tracing::debug;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Prefix metric names start with unless another one is chosen
+pub const DEFAULT_STATSD_PREFIX: &str = "blurt";
+
+/// Sends StatsD metrics over UDP
+///
+/// Each notification increments `<prefix>.notifications.<bundle_id>`
+/// and each check of the databases reports its duration as the
+/// `<prefix>.poll` timing. The socket never blocks, so a metric that
+/// can't be sent right away is dropped rather than holding up the
+/// daemon, the way StatsD expects of its clients.
+pub struct StatsdSink {
+    socket: UdpSocket,
+    prefix: String,
+}
+
+impl StatsdSink {
+    /// Send metrics to the StatsD server at `addr`, e.g. 127.0.0.1:8125
+    pub fn new(addr: &str, prefix: &str) -> Result<Self, BlurtError> {
+        let server = addr.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| BlurtError::Sink(format!("{} doesn't resolve to an address", addr)))?;
+        let local: SocketAddr = match server {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, prefix: prefix.trim_end_matches('.').to_string() })
+    }
+
+    /// Send a single metric line, e.g. `notifications.com.apple.iCal:1|c`
+    fn send(&self, metric: &str) -> std::io::Result<()> {
+        let line = if self.prefix.is_empty() {
+            metric.to_string()
+        } else {
+            format!("{}.{}", self.prefix, metric)
+        };
+        self.socket.send(line.as_bytes()).map(|_| ())
+    }
+}
+
+#[async_trait]
+impl NotificationSink for StatsdSink {
+    fn name(&self) -> &str {
+        "statsd"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let bundle_id = metric_segment(notification.bundle_id.as_deref().unwrap_or("unknown"));
+        self.send(&format!("notifications.{}:1|c", bundle_id))
+            .map_err(|e| BlurtError::Sink(format!("failed to send StatsD counter: {}", e)))
+    }
+
+    fn record_poll(&self, latency: Duration) {
+        if let Err(e) = self.send(&format!("poll:{}|ms", latency.as_millis())) {
+            debug!(error = %e, "Failed to send StatsD timing");
+        }
+    }
+}
+
+/// Replace the characters StatsD uses to separate a metric's parts
+fn metric_segment(name: &str) -> String {
+    name.chars()
+        .map(|c| if matches!(c, ':' | '|' | '@' | '#') || c.is_whitespace() { '_' } else { c })
+        .collect()
+}
@@ -0,0 +1,58 @@
+//! Sink that prints notifications to stdout.
+
+use async_trait::async_trait;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use crate::timezone::DisplayTimezone;
+use super::NotificationSink;
+
+/// Prints each notification to stdout, as a line of JSON unless text was chosen
+#[derive(Debug, Default)]
+pub struct StdoutSink {
+    pretty: bool,
+    /// Timezone of the dates and whether to color text output
+    text: Option<(DisplayTimezone, bool)>,
+}
+
+impl StdoutSink {
+    /// Indent each notification over several lines to be read by eye
+    ///
+    /// The output is then a stream of JSON objects rather than JSON
+    /// Lines, which tools like `jq` still read.
+    pub fn with_pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Print each notification as human readable text instead of JSON
+    ///
+    /// Notifications are separated by a blank line and their dates are
+    /// shown in the timezone. With `color` the text is styled with ANSI
+    /// escapes, for terminals.
+    pub fn with_text(mut self, timezone: DisplayTimezone, color: bool) -> Self {
+        self.text = Some((timezone, color));
+        self
+    }
+}
+
+#[async_trait]
+impl NotificationSink for StdoutSink {
+    fn name(&self) -> &str {
+        "stdout"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        if let Some((timezone, color)) = self.text {
+            println!("{}\n", notification.describe(timezone, color));
+            return Ok(());
+        }
+
+        let json = if self.pretty {
+            serde_json::to_string_pretty(notification)
+        } else {
+            serde_json::to_string(notification)
+        };
+        println!("{}", json.map_err(|e| BlurtError::Sink(e.to_string()))?);
+        Ok(())
+    }
+}
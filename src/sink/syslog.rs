@@ -0,0 +1,67 @@
+//! Sink that writes notifications to the system log.
+
+use async_trait::async_trait;
+use std::sync::Mutex;
+use syslog::{Facility, Formatter3164, Logger, LoggerBackend};
+use tracing::warn;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Writes each notification to syslog
+///
+/// If the syslog socket can't be reached, notifications are written
+/// to stderr instead so they're never silently lost.
+pub struct SyslogSink {
+    logger: Option<Mutex<Logger<LoggerBackend, Formatter3164>>>,
+}
+
+impl SyslogSink {
+    /// Connect to the local syslog socket with the given facility
+    pub fn new(facility: Facility) -> Self {
+        let formatter = Formatter3164 {
+            facility,
+            hostname: None,
+            process: "blurt".to_string(),
+            pid: std::process::id(),
+        };
+
+        let logger = match syslog::unix(formatter) {
+            Ok(logger) => Some(Mutex::new(logger)),
+            Err(e) => {
+                warn!(error = %e, "Failed to connect to syslog, writing notifications to stderr");
+                None
+            }
+        };
+
+        Self { logger }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for SyslogSink {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let message = format_message(notification);
+
+        if let Some(logger) = &self.logger {
+            let mut logger = logger.lock().map_err(|e| BlurtError::Sink(e.to_string()))?;
+            match logger.info(&message) {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!(error = %e, "Failed to write to syslog, writing notification to stderr"),
+            }
+        }
+
+        eprintln!("{}", message);
+        Ok(())
+    }
+}
+
+/// Format a notification with the title as the summary and the body as the message
+fn format_message(notification: &Notification) -> String {
+    let app = notification.app_name.as_deref().or(notification.bundle_id.as_deref()).unwrap_or("unknown");
+    format!("[{}] {}: {}", app, notification.title, notification.body)
+}
@@ -0,0 +1,104 @@
+//! Sink that sends notifications to a Telegram chat.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Instant};
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Longest message the Bot API accepts, in characters
+const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Telegram allows about one message per second to the same chat
+const MIN_SEND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Sends each notification as a message from a Telegram bot
+pub struct TelegramSink {
+    client: Client,
+    token: String,
+    chat_id: String,
+    last_sent: Mutex<Option<Instant>>,
+}
+
+impl TelegramSink {
+    /// Create a sink that sends messages with the bot token to the chat
+    pub fn new(token: String, chat_id: String) -> Self {
+        Self {
+            client: Client::new(),
+            token,
+            chat_id,
+            last_sent: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for TelegramSink {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        // Hold the lock while sending so messages are spaced out
+        let mut last_sent = self.last_sent.lock().await;
+        if let Some(last_sent) = *last_sent {
+            let elapsed = last_sent.elapsed();
+            if elapsed < MIN_SEND_INTERVAL {
+                sleep(MIN_SEND_INTERVAL - elapsed).await;
+            }
+        }
+
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.token);
+        let response = self.client.post(url)
+            .timeout(Duration::from_secs(10))
+            .json(&serde_json::json!({
+                "chat_id": self.chat_id,
+                "text": format_message(notification),
+                "parse_mode": "HTML",
+            }))
+            .send()
+            .await
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+        *last_sent = Some(Instant::now());
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(BlurtError::Sink(format!("Telegram API returned {}: {}", status, body)));
+        }
+
+        Ok(())
+    }
+}
+
+/// Format the message with the title bolded, the body below and the app as a footer
+fn format_message(notification: &Notification) -> String {
+    let title = format!("<b>{}</b>\n", escape_html(&notification.title));
+    let footer = format!("\n\n<i>{}</i>", escape_html(notification.app_name.as_deref().or(notification.bundle_id.as_deref()).unwrap_or("unknown")));
+
+    // Truncate the body so the whole message fits in the limit
+    let available = MAX_MESSAGE_LENGTH.saturating_sub(title.chars().count() + footer.chars().count());
+    let body = escape_html(&notification.body);
+    let body = if body.chars().count() > available {
+        let mut truncated: String = body.chars().take(available.saturating_sub(1)).collect();
+        // Don't leave a partial HTML entity behind
+        if let Some(amp) = truncated.rfind('&')
+            && !truncated[amp..].contains(';') {
+                truncated.truncate(amp);
+            }
+        truncated.push('…');
+        truncated
+    } else {
+        body
+    };
+
+    format!("{}{}{}", title, body, footer)
+}
+
+/// Escape the characters Telegram's HTML parse mode treats specially
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
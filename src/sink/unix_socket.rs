@@ -0,0 +1,104 @@
+//! Sink that writes notifications to a Unix domain socket.
+
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Number of notifications held while the listener is unavailable
+const PENDING_BUFFER: usize = 1024;
+
+/// Delay before the first reconnect attempt, doubled on each failure
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Longest delay between reconnect attempts
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Writes each notification as a line of JSON to a Unix socket
+///
+/// Writes happen in a background task that reconnects whenever the
+/// listener goes away. Notifications are held until it's back rather
+/// than dropped, up to `PENDING_BUFFER` of them.
+pub struct UnixSocketSink {
+    sender: mpsc::Sender<String>,
+    writer: JoinHandle<()>,
+}
+
+impl UnixSocketSink {
+    /// Connect to the listener at `path`, retrying until it's available
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let (sender, receiver) = mpsc::channel(PENDING_BUFFER);
+        Self {
+            sender,
+            writer: tokio::spawn(write_lines(path.into(), receiver)),
+        }
+    }
+}
+
+impl Drop for UnixSocketSink {
+    fn drop(&mut self) {
+        self.writer.abort();
+    }
+}
+
+#[async_trait]
+impl NotificationSink for UnixSocketSink {
+    fn name(&self) -> &str {
+        "unix-socket"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        let mut line = serde_json::to_string(notification)
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+        line.push('\n');
+
+        match self.sender.try_send(line) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(BlurtError::Sink("unix socket listener is unavailable and the buffer is full".to_string())),
+            Err(TrySendError::Closed(_)) => Err(BlurtError::Sink("unix socket writer stopped".to_string())),
+        }
+    }
+}
+
+/// Write each line to the socket, reconnecting and retrying the line on failure
+async fn write_lines(path: PathBuf, mut lines: mpsc::Receiver<String>) {
+    let mut stream: Option<UnixStream> = None;
+    while let Some(line) = lines.recv().await {
+        let mut backoff = RECONNECT_BACKOFF;
+        loop {
+            if stream.is_none() {
+                match UnixStream::connect(&path).await {
+                    Ok(connected) => {
+                        debug!(path = %path.display(), "Connected to unix socket");
+                        stream = Some(connected);
+                    }
+                    Err(e) => {
+                        debug!(path = %path.display(), error = %e, ?backoff, "Unix socket unavailable, retrying");
+                        sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_RECONNECT_BACKOFF);
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(connected) = &mut stream {
+                match connected.write_all(line.as_bytes()).await {
+                    Ok(()) => break,
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "Failed to write to unix socket, reconnecting");
+                        stream = None;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Sink that forwards notifications to a webhook.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use std::time::Duration;
+use crate::database::Notification;
+use crate::error::BlurtError;
+use super::NotificationSink;
+
+/// Forwards each notification to a webhook URL via HTTP POST
+///
+/// When batching, the notifications found in a check are posted
+/// together as a JSON array instead.
+pub struct WebhookSink {
+    client: Client,
+    url: String,
+}
+
+impl WebhookSink {
+    /// Create a sink that posts JSON notifications to the URL
+    pub fn new(url: String) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        self.client.post(&self.url)
+            .timeout(Duration::from_secs(5))
+            .json(notification)
+            .send()
+            .await
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn handle_batch(&self, notifications: &[Notification]) -> Result<(), BlurtError> {
+        self.client.post(&self.url)
+            .timeout(Duration::from_secs(5))
+            .json(notifications)
+            .send()
+            .await
+            .map_err(|e| BlurtError::Sink(e.to_string()))?;
+
+        Ok(())
+    }
+}
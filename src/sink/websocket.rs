@@ -0,0 +1,132 @@
+//! Sink that broadcasts notifications to WebSocket clients.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tracing::{debug, warn};
+use crate::database::Notification;
+use crate::error::BlurtError;
+use crate::recent::RecentNotifications;
+use super::NotificationSink;
+use super::broadcast::{accept_clients, Broadcaster, Event, Replay};
+
+/// Pushes each notification as a JSON message to every connected client
+///
+/// Clients connect to the listener with a plain WebSocket handshake,
+/// adding `?backfill=N` to the URL to first receive up to that many
+/// of the daemon's recent notifications. Each one is served by its
+/// own task so a slow client never blocks the daemon, it skips the
+/// messages it fell too far behind on instead.
+pub struct WebSocketSink {
+    broadcaster: Arc<Broadcaster>,
+    server: JoinHandle<()>,
+}
+
+impl WebSocketSink {
+    /// Accept WebSocket clients on the listener, backfilling from `recent`
+    pub fn new(listener: TcpListener, recent: Arc<RecentNotifications>) -> Self {
+        let broadcaster = Arc::new(Broadcaster::new(recent));
+        let clients = broadcaster.clone();
+        let server = tokio::spawn(accept_clients(listener, "websocket", move |stream| serve_client(stream, clients.clone())));
+        Self { broadcaster, server }
+    }
+}
+
+impl Drop for WebSocketSink {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebSocketSink {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+        self.broadcaster.send(std::slice::from_ref(notification));
+        Ok(())
+    }
+
+    async fn handle_batch(&self, notifications: &[Notification]) -> Result<(), BlurtError> {
+        self.broadcaster.send(notifications);
+        Ok(())
+    }
+}
+
+/// Forward notifications to a client until it disconnects
+async fn serve_client(stream: TcpStream, broadcaster: Arc<Broadcaster>) {
+    // Subscribing during the handshake means nothing dispatched once
+    // the client sees it complete is missed
+    let mut subscribed = None;
+    // The error type is tungstenite's, the callback never returns it
+    #[allow(clippy::result_large_err)]
+    let handshake = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        let backfill = requested_backfill(request);
+        debug!(backfill, "WebSocket client subscribed");
+        subscribed = Some(broadcaster.subscribe(Replay::Last(backfill)));
+        Ok(response)
+    });
+    let mut socket = match handshake.await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!(error = %e, "WebSocket handshake failed");
+            return;
+        }
+    };
+    let Some((mut notifications, missed)) = subscribed else {
+        return;
+    };
+
+    for event in &missed {
+        if send_event(&mut socket, event).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            event = notifications.recv() => match event {
+                Some(event) => {
+                    if send_event(&mut socket, &event).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            // Incoming messages are ignored, only a close or error ends the connection
+            message = socket.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    debug!("WebSocket client disconnected");
+}
+
+/// Read `backfill` from the handshake's query string, zero when it's missing or invalid
+fn requested_backfill(request: &Request) -> usize {
+    request.uri().query()
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("backfill="))
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Send an event's notification as a JSON text message
+async fn send_event<S>(socket: &mut S, event: &Event) -> Result<(), BlurtError>
+where
+    S: futures::Sink<Message> + Unpin,
+{
+    let json = serde_json::to_string(&event.notification)
+        .map_err(|e| BlurtError::Sink(e.to_string()))?;
+    socket.send(Message::text(json)).await
+        .map_err(|_| BlurtError::Sink("WebSocket client disconnected".to_string()))
+}
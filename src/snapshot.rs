@@ -0,0 +1,87 @@
+//! Copies of the live database read instead of the original.
+//!
+//! The OS holds locks on the notification database while it writes
+//! to it. Reading a private copy of the database and its `-wal` and
+//! `-shm` sidecars never contends for those locks, at the cost of
+//! copying the files before every check.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{debug, warn};
+use crate::error::BlurtError;
+
+/// Suffixes of the files SQLite keeps next to a database in WAL mode
+const SIDECARS: [&str; 2] = ["-wal", "-shm"];
+
+/// A copy of a database in its own temporary directory
+///
+/// The directory is created on the first refresh and removed along
+/// with the copy when the snapshot is dropped.
+#[derive(Debug)]
+pub struct Snapshot {
+    source: PathBuf,
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl Snapshot {
+    /// Snapshot the database at `source` into a new temporary directory
+    pub fn new(source: impl Into<PathBuf>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let source = source.into();
+        let dir = std::env::temp_dir().join(format!(
+            "blurt-snapshot-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        ));
+        let path = dir.join(source.file_name().unwrap_or("db".as_ref()));
+        Self { source, dir, path }
+    }
+
+    /// Get the path of the copy
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Copy the database and its sidecars over the previous copy
+    ///
+    /// A sidecar that no longer exists next to the database is
+    /// removed from the copy so a stale write-ahead log is never
+    /// replayed over the new copy.
+    pub async fn refresh(&self) -> Result<(), BlurtError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::copy(&self.source, &self.path).await?;
+
+        for suffix in SIDECARS {
+            let source = with_suffix(&self.source, suffix);
+            let copy = with_suffix(&self.path, suffix);
+            match tokio::fs::copy(&source, &copy).await {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    let _ = tokio::fs::remove_file(&copy).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        debug!(path = %self.path.display(), "Refreshed database snapshot");
+        Ok(())
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        match std::fs::remove_dir_all(&self.dir) {
+            Ok(()) => debug!(path = %self.dir.display(), "Removed database snapshot"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!(path = %self.dir.display(), error = %e, "Failed to remove database snapshot"),
+        }
+    }
+}
+
+/// Append a suffix to a path's file name, e.g. `db` to `db-wal`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
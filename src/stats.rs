@@ -0,0 +1,37 @@
+//! Per-app notification counts for the `stats` command.
+
+use std::collections::HashMap;
+use crate::database::NotificationDatabase;
+use crate::error::BlurtError;
+
+/// How many notifications an app has in the database
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct AppCount {
+    /// `None` for notifications whose app couldn't be resolved
+    pub bundle_id: Option<String>,
+    pub count: u64,
+}
+
+/// Count the notifications in every database by bundle identifier
+///
+/// Counts are sorted with the noisiest app first, ties broken by
+/// bundle identifier.
+pub async fn app_counts(dbs: &[NotificationDatabase]) -> Result<Vec<AppCount>, BlurtError> {
+    let mut counts: HashMap<Option<String>, u64> = HashMap::new();
+    for db in dbs {
+        if !db.exists() {
+            return Err(BlurtError::DatabaseNotFound(db.db_path().to_string()));
+        }
+
+        for notification in db.all_notifications().await? {
+            *counts.entry(notification.bundle_id).or_default() += 1;
+        }
+    }
+
+    let mut counts: Vec<AppCount> = counts.into_iter()
+        .map(|(bundle_id, count)| AppCount { bundle_id, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.bundle_id.cmp(&b.bundle_id)));
+
+    Ok(counts)
+}
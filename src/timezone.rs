@@ -0,0 +1,52 @@
+//! Timezone dates are shown in by human readable output.
+
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
+use std::str::FromStr;
+
+/// Format of dates in human readable output
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %Z";
+
+/// Timezone to show dates in, UTC unless chosen otherwise
+///
+/// Only affects output meant for people. JSON and CSV always carry
+/// the UTC timestamp so they stay the same wherever they're produced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisplayTimezone {
+    #[default]
+    Utc,
+    /// The system's timezone
+    Local,
+    /// An IANA timezone, e.g. `Europe/Paris`
+    Named(Tz),
+}
+
+impl DisplayTimezone {
+    /// Format a date in the timezone, e.g. `2024-05-01 14:30:00 CEST`
+    pub fn format(&self, date: DateTime<Utc>) -> String {
+        match self {
+            Self::Utc => date.format(DATE_FORMAT).to_string(),
+            // Local has no abbreviation so show the offset instead
+            Self::Local => date.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S %:z").to_string(),
+            Self::Named(tz) => date.with_timezone(tz).format(DATE_FORMAT).to_string(),
+        }
+    }
+}
+
+impl FromStr for DisplayTimezone {
+    type Err = String;
+
+    /// Parse `UTC`, `local` or an IANA timezone name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("utc") {
+            return Ok(Self::Utc);
+        }
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+
+        s.parse::<Tz>()
+            .map(Self::Named)
+            .map_err(|_| format!("Unknown timezone '{}': expected an IANA name like Europe/Paris, UTC or local", s))
+    }
+}
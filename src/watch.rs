@@ -0,0 +1,89 @@
+//! Watch database files to pick up changes without waiting for the next poll.
+//!
+//! SQLite's `update_hook` only fires for writes made through the
+//! connection it's registered on, so it never sees the OS writing
+//! to the notification database from another process. Watching the
+//! database and its write-ahead log on disk is the closest we can
+//! get to being told about inserts as they happen.
+
+use std::ffi::OsString;
+use std::path::Path;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+use crate::error::BlurtError;
+
+/// Wakes the daemon when a monitored database changes on disk
+pub struct DatabaseWatcher {
+    /// Kept alive so the OS keeps delivering events
+    _watcher: RecommendedWatcher,
+    changes: mpsc::UnboundedReceiver<()>,
+}
+
+impl DatabaseWatcher {
+    /// Watch each database for writes
+    ///
+    /// The parent directory is watched rather than the file itself
+    /// so changes to the `-wal` file and replacements of the
+    /// database are also seen. The `-shm` file is ignored since
+    /// readers touch it too.
+    pub fn new<'a>(db_paths: impl IntoIterator<Item = &'a str>) -> Result<Self, BlurtError> {
+        let mut names: Vec<OsString> = Vec::new();
+        let mut dirs = Vec::new();
+        for db_path in db_paths {
+            let path = Path::new(db_path);
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let mut wal = name.to_os_string();
+            wal.push("-wal");
+            names.push(name.to_os_string());
+            names.push(wal);
+
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => Path::new(".").to_path_buf(),
+            };
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+
+        let (tx, changes) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            match res {
+                Ok(event) => {
+                    let is_write = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+                    let is_database = event.paths.iter()
+                        .any(|path| path.file_name().is_some_and(|name| names.iter().any(|n| n == name)));
+                    if is_write && is_database {
+                        let _ = tx.send(());
+                    }
+                }
+                Err(e) => warn!(error = %e, "Failed to watch database"),
+            }
+        })?;
+
+        for dir in &dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            debug!(path = %dir.display(), "Watching for database changes");
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Wait until a monitored database changes
+    ///
+    /// A single write usually produces a burst of events so any
+    /// others already pending are discarded.
+    pub async fn changed(&mut self) {
+        if self.changes.recv().await.is_none() {
+            // The watcher has stopped, leave it to the poll interval
+            std::future::pending::<()>().await;
+        }
+        while self.changes.try_recv().is_ok() {}
+    }
+}
@@ -0,0 +1,103 @@
+//! Tests for command line argument handling.
+
+use blurt::cli::expand_home;
+use std::path::PathBuf;
+
+#[test]
+fn test_expand_home() {
+    assert_eq!(expand_home("~/notes/db", Some("/Users/me")).unwrap(), PathBuf::from("/Users/me/notes/db"));
+    assert_eq!(expand_home("~", Some("/Users/me")).unwrap(), PathBuf::from("/Users/me"));
+    assert_eq!(expand_home("/tmp/db", Some("/Users/me")).unwrap(), PathBuf::from("/tmp/db"));
+}
+
+#[test]
+fn test_expand_home_without_home() {
+    let err = expand_home("~/notes/db", None).unwrap_err();
+    assert!(err.contains("HOME is not set"));
+
+    // Paths without a tilde don't need HOME
+    assert_eq!(expand_home("/tmp/db", None).unwrap(), PathBuf::from("/tmp/db"));
+}
+
+#[test]
+fn test_expand_home_rejects_other_users() {
+    let err = expand_home("~alice/db", Some("/Users/me")).unwrap_err();
+    assert!(err.contains("another user's home directory"));
+}
+
+#[test]
+fn test_discover_db_path() {
+    use blurt::cli::discover_db_path;
+
+    let home = tempfile::TempDir::new().unwrap();
+    assert_eq!(discover_db_path(home.path()), None);
+
+    let containers = home.path().join("Library/Group Containers");
+    std::fs::create_dir_all(containers.join("group.com.apple.usernoted/db")).unwrap();
+    std::fs::write(containers.join("group.com.apple.usernoted/db/db"), b"").unwrap();
+    assert_eq!(discover_db_path(home.path()), Some(containers.join("group.com.apple.usernoted/db/db")));
+
+    // A newer db directory wins, but only once it holds a database
+    std::fs::create_dir_all(containers.join("group.com.apple.usernoted/db10")).unwrap();
+    std::fs::create_dir_all(containers.join("group.com.apple.usernoted/db3")).unwrap();
+    std::fs::write(containers.join("group.com.apple.usernoted/db3/db"), b"").unwrap();
+    assert_eq!(discover_db_path(home.path()), Some(containers.join("group.com.apple.usernoted/db3/db")));
+    std::fs::write(containers.join("group.com.apple.usernoted/db10/db"), b"").unwrap();
+    assert_eq!(discover_db_path(home.path()), Some(containers.join("group.com.apple.usernoted/db10/db")));
+}
+
+#[test]
+fn test_log_level_flags() {
+    use blurt::cli::Args;
+    use clap::Parser;
+
+    let log_level = |flags: &[&str]| {
+        let args = Args::try_parse_from(std::iter::once("blurt").chain(flags.iter().copied())).unwrap();
+        args.log_level()
+    };
+    assert_eq!(log_level(&[]), None);
+    assert_eq!(log_level(&["-v"]), Some("debug"));
+    assert_eq!(log_level(&["-vv"]), Some("trace"));
+    assert_eq!(log_level(&["-q"]), Some("warn"));
+    assert_eq!(log_level(&["-q", "-q"]), Some("error"));
+    assert_eq!(log_level(&["stats", "-v"]), Some("debug"));
+
+    assert!(Args::try_parse_from(["blurt", "-v", "-q"]).is_err());
+}
+
+#[test]
+fn test_color_choice() {
+    use blurt::cli::ColorChoice;
+
+    assert!(ColorChoice::Auto.enabled_for(None, true));
+    assert!(ColorChoice::Auto.enabled_for(Some(""), true));
+    // Piped output and NO_COLOR turn it off
+    assert!(!ColorChoice::Auto.enabled_for(None, false));
+    assert!(!ColorChoice::Auto.enabled_for(Some("1"), true));
+    // Unless it was asked for explicitly
+    assert!(ColorChoice::Always.enabled_for(Some("1"), false));
+    assert!(!ColorChoice::Never.enabled_for(None, true));
+}
+
+#[test]
+fn test_fields_must_be_known() {
+    use blurt::cli::Args;
+    use clap::Parser;
+
+    let args = Args::try_parse_from(["blurt", "--fields", "title, body,bundle_id"]).unwrap();
+    assert_eq!(args.fields, vec!["title", "body", "bundle_id"]);
+
+    let err = Args::try_parse_from(["blurt", "--fields", "title,sender"]).unwrap_err().to_string();
+    assert!(err.contains("unknown field 'sender'"), "{}", err);
+}
+
+#[test]
+fn test_has_output_covers_every_output_arg() {
+    use blurt::cli::Args;
+    use clap::Parser;
+
+    // Checks every id in OUTPUT_ARGS, panicking on one it doesn't know
+    assert!(!Args::parse_from(["blurt"]).has_output());
+    assert!(Args::parse_from(["blurt", "--sse-addr", "127.0.0.1:9002"]).has_output());
+    assert!(Args::parse_from(["blurt", "--notify"]).has_output());
+}
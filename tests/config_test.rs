@@ -0,0 +1,99 @@
+//! Tests for reading options from a configuration file.
+
+use blurt::cli::Args;
+use blurt::config::Config;
+use clap::{CommandFactory, FromArgMatches};
+use std::path::PathBuf;
+
+/// Parse the command line and apply the config file on top
+fn args_with_config(argv: &[&str], config: &str) -> Result<Args, String> {
+    let matches = Args::command().get_matches_from(argv);
+    let mut args = Args::from_arg_matches(&matches).unwrap();
+    Config::parse(config)?.apply(&mut args, &matches)?;
+    Ok(args)
+}
+
+#[test]
+fn test_config_fills_in_options() {
+    let args = args_with_config(&["blurt"], r#"
+        db-paths = ["/tmp/notifications.db"]
+        interval = 2
+        max-interval = 60
+        only = ["com.apple.MobileSMS"]
+        match-body = ["OTP"]
+        quiet = "22:00-07:00"
+        webhook = "https://example.com/hook"
+    "#).unwrap();
+
+    assert_eq!(args.db_paths, vec![PathBuf::from("/tmp/notifications.db")]);
+    assert_eq!(args.interval, 2);
+    assert_eq!(args.max_interval, Some(60));
+    assert_eq!(args.allowlist, vec!["com.apple.MobileSMS"]);
+    assert!(args.match_body[0].is_match("Your OTP"));
+    assert!(args.quiet.is_some());
+    assert_eq!(args.webhook.as_deref(), Some("https://example.com/hook"));
+}
+
+#[test]
+fn test_command_line_overrides_config() {
+    let args = args_with_config(
+        &["blurt", "--interval", "3", "--log-file", "/tmp/blurt.log"],
+        r#"
+            interval = 2
+            dedup-window = 10
+            webhook = "https://example.com/hook"
+        "#,
+    ).unwrap();
+
+    assert_eq!(args.interval, 3);
+    assert_eq!(args.dedup_window, 10);
+    // Choosing an output on the command line replaces the file's
+    assert_eq!(args.webhook, None);
+    assert_eq!(args.log_file, Some(PathBuf::from("/tmp/blurt.log")));
+}
+
+#[test]
+fn test_invalid_config_names_the_field() {
+    let err = Config::parse("intervall = 2").unwrap_err();
+    assert!(err.contains("intervall"), "{}", err);
+
+    let err = Config::parse("interval = \"fast\"").unwrap_err();
+    assert!(err.contains("interval"), "{}", err);
+
+    let err = Config::parse("telegram-token = \"123:ABC\"").unwrap_err();
+    assert!(err.contains("telegram-chat"), "{}", err);
+
+    // Outputs can be combined
+    let config = Config::parse("notify = true\nwebhook = \"https://example.com/hook\"").unwrap();
+    assert_eq!(config.notify, Some(true));
+    assert_eq!(config.webhook.as_deref(), Some("https://example.com/hook"));
+
+    let err = args_with_config(&["blurt"], "match-title = [\"(\"]").unwrap_err();
+    assert!(err.contains("match-title"), "{}", err);
+}
+
+#[test]
+fn test_config_is_checked_against_the_command_line() {
+    let err = args_with_config(&["blurt"], "quiet-queue = true").unwrap_err();
+    assert!(err.contains("quiet"), "{}", err);
+
+    // The option required can come from either
+    let args = args_with_config(&["blurt", "--quiet", "22:00-07:00"], "quiet-queue = true").unwrap();
+    assert!(args.quiet_queue);
+
+    let err = args_with_config(&["blurt", "--webhook", "https://example.com/hook"], "json-pretty = true").unwrap_err();
+    assert!(err.contains("json-pretty"), "{}", err);
+
+    let err = args_with_config(&["blurt"], "text = true\njson-pretty = true").unwrap_err();
+    assert!(err.contains("text"), "{}", err);
+
+    let err = args_with_config(&["blurt", "--once"], "from-beginning = true").unwrap_err();
+    assert!(err.contains("from-beginning"), "{}", err);
+
+    // A file's output options are fine when the command line replaces its outputs
+    let args = args_with_config(
+        &["blurt", "--notify"],
+        "mqtt-url = \"mqtt://localhost\"\nmqtt-topic = \"alerts\"",
+    ).unwrap();
+    assert_eq!(args.mqtt_url, None);
+}
@@ -141,14 +141,14 @@ async fn test_daemon_integration_with_mock_db() {
 
     // First check should set initial rowid
     daemon.check_for_new_notifications().await.unwrap();
-    assert_eq!(daemon.last_rowid, Some(1));
+    assert_eq!(daemon.last_rowid(), Some(1));
 
     // Insert a new notification
     insert_notification(&db, 2, 1, "New Notification", "New message", "com.example.testapp", 1234567891.0).await;
 
     // Second check should detect the new notification
     daemon.check_for_new_notifications().await.unwrap();
-    assert_eq!(daemon.last_rowid, Some(2));
+    assert_eq!(daemon.last_rowid(), Some(2));
 }
 
 #[tokio::test]
@@ -168,7 +168,7 @@ async fn test_daemon_deletion_scenario() {
 
     // First check - sets initial rowid to 2
     daemon.check_for_new_notifications().await.unwrap();
-    assert_eq!(daemon.last_rowid, Some(2));
+    assert_eq!(daemon.last_rowid(), Some(2));
 
     // Delete all notifications (user dismisses them)
     let conn = db.connect().await.unwrap();
@@ -184,5 +184,1932 @@ async fn test_daemon_deletion_scenario() {
     daemon.check_for_new_notifications().await.unwrap();
 
     // The last_rowid should be updated to 1
-    assert_eq!(daemon.last_rowid, Some(1));
+    assert_eq!(daemon.last_rowid(), Some(1));
+}
+
+#[tokio::test]
+async fn test_app_identifier_lookup() {
+    let (_temp_dir, db) = create_test_database().await;
+
+    let conn = db.connect().await.unwrap();
+    conn.call(|db_conn| {
+        db_conn.execute("INSERT INTO app (app_id, identifier) VALUES (7, 'com.apple.iCal')", [])?;
+        Ok(())
+    }).await.unwrap();
+
+    let identifier = blurt::database::app_identifier(&conn, 7).await.unwrap();
+    assert_eq!(identifier, Some("com.apple.iCal".to_string()));
+
+    let missing = blurt::database::app_identifier(&conn, 8).await.unwrap();
+    assert_eq!(missing, None);
+}
+
+#[tokio::test]
+async fn test_list_apps() {
+    use blurt::database::App;
+
+    let (_temp_dir, db) = create_test_database().await;
+    assert!(db.list_apps().await.unwrap().is_empty());
+
+    db.connect().await.unwrap().call(|db_conn| {
+        db_conn.execute_batch(
+            "ALTER TABLE app ADD COLUMN name VARCHAR;
+             INSERT INTO app (app_id, identifier, name) VALUES (1, 'com.tinyspeck.slackmacgap', 'Slack');
+             INSERT INTO app (app_id, identifier) VALUES (2, NULL);
+             INSERT INTO app (app_id, identifier, name) VALUES (3, 'com.apple.iCal', 'Calendar');"
+        )?;
+        Ok(())
+    }).await.unwrap();
+
+    let app = |identifier: Option<&str>, name: Option<&str>| App {
+        identifier: identifier.map(str::to_string),
+        name: name.map(str::to_string),
+    };
+    assert_eq!(db.list_apps().await.unwrap(), vec![
+        (3, app(Some("com.apple.iCal"), Some("Calendar"))),
+        (1, app(Some("com.tinyspeck.slackmacgap"), Some("Slack"))),
+        (2, app(None, None)),
+    ]);
+}
+
+#[tokio::test]
+async fn test_app_name_resolution() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    // Without a name column only the identifier is available
+    let conn = db.connect().await.unwrap();
+    conn.call(|db_conn| {
+        db_conn.execute("INSERT INTO app (app_id, identifier) VALUES (7, 'com.apple.iCal')", [])?;
+        Ok(())
+    }).await.unwrap();
+    let app = blurt::database::app_info(&conn, 7).await.unwrap().unwrap();
+    assert_eq!(app.identifier.as_deref(), Some("com.apple.iCal"));
+    assert_eq!(app.name, None);
+
+    conn.call(|db_conn| {
+        db_conn.execute_batch("ALTER TABLE app ADD COLUMN name VARCHAR; UPDATE app SET name = 'Calendar' WHERE app_id = 7")?;
+        Ok(())
+    }).await.unwrap();
+    insert_notification(&db, 1, 7, "Standup", "In 5 minutes", "com.apple.iCal", 1234567890.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(n.app_name.clone()));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec![Some("Calendar".to_string())]);
+
+    // Exports resolve it the same way
+    let exported = blurt::export::read_notifications(std::slice::from_ref(&db), None).await.unwrap();
+    assert_eq!(exported[0].app_name.as_deref(), Some("Calendar"));
+}
+
+#[tokio::test]
+async fn test_run_once_processes_existing_notifications() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1234567891.0).await;
+
+    let mut daemon = NotificationDaemon::new(&db_path);
+    daemon.run_once().await.unwrap();
+
+    // Every existing record was processed rather than used as a baseline
+    assert_eq!(daemon.last_rowid(), Some(2));
+}
+
+#[tokio::test]
+async fn test_from_beginning_processes_existing_notifications() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1234567891.0).await;
+
+    let mut daemon = NotificationDaemon::new(&db_path).with_from_beginning(true);
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(2));
+    assert!(daemon.metrics().render().contains("blurt_notifications_processed_total 2"));
+
+    // Later checks only pick up new notifications
+    insert_notification(&db, 3, 1, "Third", "Message 3", "com.example.testapp", 1234567892.0).await;
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(3));
+    assert!(daemon.metrics().render().contains("blurt_notifications_processed_total 3"));
+}
+
+#[tokio::test]
+async fn test_run_tail_emits_most_recent_in_order() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1234567891.0).await;
+    insert_notification(&db, 3, 1, "Third", "Message 3", "com.example.testapp", 1234567892.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(n.title.clone()));
+    daemon.run_tail(2).await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec!["Second", "Third"]);
+}
+
+#[test]
+fn test_dedup_drops_identical_notifications_within_window() {
+    use blurt::database::Notification;
+    use blurt::dedup::Deduplicator;
+    use std::time::Duration;
+
+    let notification = Notification {
+        id: 1,
+        title: "Build failed".to_string(),
+        body: "main is red".to_string(),
+        bundle_id: Some("com.example.ci".to_string()),
+        ..Default::default()
+    };
+    // Same content under a different ROWID is still a duplicate
+    let repost = Notification { id: 2, ..notification.clone() };
+    let different = Notification { id: 3, body: "main is green".to_string(), ..notification.clone() };
+
+    let mut dedup = Deduplicator::new(Duration::from_secs(60));
+    assert!(!dedup.is_duplicate(&notification));
+    assert!(dedup.is_duplicate(&repost));
+    assert!(!dedup.is_duplicate(&different));
+
+    // A zero window disables deduplication
+    let mut disabled = Deduplicator::new(Duration::ZERO);
+    assert!(!disabled.is_duplicate(&notification));
+    assert!(!disabled.is_duplicate(&repost));
+}
+
+#[test]
+fn test_dedup_key_chooses_what_is_identical() {
+    use blurt::database::Notification;
+    use blurt::dedup::{DedupKey, Deduplicator};
+    use std::time::Duration;
+
+    let message = Notification {
+        id: 1,
+        uuid: Some("A".to_string()),
+        title: "Alice".to_string(),
+        body: "Lunch?".to_string(),
+        bundle_id: Some("com.apple.MobileSMS".to_string()),
+        thread_id: Some("alice".to_string()),
+        ..Default::default()
+    };
+    let reply = Notification { id: 2, uuid: Some("B".to_string()), body: "Noon?".to_string(), ..message.clone() };
+    let reread = Notification { id: 3, body: "Edited".to_string(), ..message.clone() };
+    let unthreaded = Notification { id: 4, uuid: Some("C".to_string()), thread_id: None, ..message.clone() };
+
+    let mut dedup = Deduplicator::new(Duration::from_secs(60)).with_key(DedupKey::Uuid);
+    assert!(!dedup.is_duplicate(&message));
+    assert!(!dedup.is_duplicate(&reply));
+    assert!(dedup.is_duplicate(&reread));
+
+    let mut dedup = Deduplicator::new(Duration::from_secs(60)).with_key(DedupKey::Thread);
+    assert!(!dedup.is_duplicate(&message));
+    assert!(dedup.is_duplicate(&reply));
+    // Without a thread the content decides
+    assert!(!dedup.is_duplicate(&unthreaded));
+    assert!(dedup.is_duplicate(&Notification { id: 5, ..unthreaded.clone() }));
+    assert!(!dedup.is_duplicate(&Notification { body: "Dinner?".to_string(), ..unthreaded }));
+}
+
+#[test]
+fn test_attachment_parsing() {
+    use blurt::daemon::parse_notification_from_plist;
+    use plist::Value;
+
+    let mut attachment = plist::Dictionary::new();
+    attachment.insert("iden".to_string(), Value::String("photo".to_string()));
+    attachment.insert("url".to_string(), Value::String("file:///tmp/photo.jpg".to_string()));
+
+    let mut req_dict = plist::Dictionary::new();
+    req_dict.insert("titl".to_string(), Value::String("Photo".to_string()));
+    req_dict.insert("atta".to_string(), Value::Array(vec![Value::Dictionary(attachment)]));
+
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.attachments, vec!["file:///tmp/photo.jpg".to_string()]);
+
+    // Notifications without attachments have an empty list
+    let plain = plist::from_bytes::<Value>(&create_test_plist_data("Title", "Body", "com.test.app", 0.0)).unwrap();
+    let notification = parse_notification_from_plist(&plain, 2).remove(0);
+    assert!(notification.attachments.is_empty());
+}
+
+#[tokio::test]
+async fn test_stream_yields_new_notifications() {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Initial Notification", "Initial message", "com.example.testapp", 1234567890.0).await;
+
+    let daemon = NotificationDaemon::new(&db_path).with_poll_interval(Duration::from_millis(50));
+    let mut notifications = Box::pin(daemon.stream());
+
+    // Give the first poll a chance to record the initial baseline
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    insert_notification(&db, 2, 1, "New Notification", "New message", "com.example.testapp", 1234567891.0).await;
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), notifications.next())
+        .await
+        .expect("timed out waiting for notification")
+        .expect("stream ended")
+        .expect("poll failed");
+    assert_eq!(notification.id, 2);
+    assert_eq!(notification.title, "New Notification");
+}
+
+#[tokio::test]
+async fn test_daemon_recovers_from_locked_database() {
+    use std::time::Duration;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Initial Notification", "Initial message", "com.example.testapp", 1234567890.0).await;
+
+    // Hold an exclusive lock like the OS does while writing
+    let locker = rusqlite::Connection::open(&db_path).unwrap();
+    locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(150));
+        locker.execute_batch("COMMIT").unwrap();
+    });
+
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_busy_timeout(Duration::from_millis(10))
+        .with_busy_retries(5);
+
+    // The first attempts hit SQLITE_BUSY but the check recovers once the lock is released
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(1));
+
+    release.join().unwrap();
+}
+
+#[tokio::test]
+async fn test_daemon_gives_up_on_locked_database() {
+    use std::time::Duration;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Initial Notification", "Initial message", "com.example.testapp", 1234567890.0).await;
+
+    let locker = rusqlite::Connection::open(&db_path).unwrap();
+    locker.execute_batch("BEGIN EXCLUSIVE").unwrap();
+
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_busy_timeout(Duration::from_millis(10))
+        .with_busy_retries(0);
+
+    let err = daemon.check_for_new_notifications().await.unwrap_err();
+    assert!(err.is_busy());
+
+    locker.execute_batch("COMMIT").unwrap();
+}
+
+#[tokio::test]
+async fn test_readonly_connection_rejects_writes() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Initial Notification", "Initial message", "com.example.testapp", 1234567890.0).await;
+
+    for readonly in [
+        blurt::database::NotificationDatabase::new(&db_path),
+        blurt::database::NotificationDatabase::new(&db_path).with_immutable(true),
+    ] {
+        let conn = readonly.connect_readonly().await.unwrap();
+        let result = conn.call(|db_conn| {
+            let count: i64 = db_conn.query_row("SELECT COUNT(*) FROM record", [], |row| row.get(0))?;
+            db_conn.execute("DELETE FROM record", [])?;
+            Ok(count)
+        }).await;
+        assert!(result.is_err());
+
+        let count = conn.call(|db_conn| {
+            Ok(db_conn.query_row("SELECT COUNT(*) FROM record", [], |row| row.get::<_, i64>(0))?)
+        }).await.unwrap();
+        assert_eq!(count, 1);
+    }
+}
+
+#[tokio::test]
+async fn test_file_sink_rotates_when_full() {
+    use blurt::database::Notification;
+    use blurt::sink::{FileSink, NotificationSink};
+
+    let temp_dir = TempDir::new().unwrap();
+    // The directory doesn't exist yet and is created by the sink
+    let path = temp_dir.path().join("logs").join("notifications.log");
+
+    let notification = Notification {
+        id: 1,
+        title: "Title".to_string(),
+        body: "Body".to_string(),
+        ..Default::default()
+    };
+    let line_len = serde_json::to_string(&notification).unwrap().len() as u64 + 1;
+
+    // Room for exactly two lines per file
+    let sink = FileSink::new(&path, line_len * 2).unwrap();
+    for _ in 0..3 {
+        sink.handle(&notification).await.unwrap();
+    }
+
+    let rotated = std::fs::read_to_string(temp_dir.path().join("logs").join("notifications.log.1")).unwrap();
+    assert_eq!(rotated.lines().count(), 2);
+    let current = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(current.lines().count(), 1);
+}
+
+#[tokio::test]
+async fn test_notifications_since() {
+    let (_temp_dir, db) = create_test_database().await;
+
+    insert_notification(&db, 1, 1, "Old", "Message 1", "com.example.testapp", 1000.0).await;
+    insert_notification(&db, 2, 1, "New", "Message 2", "com.example.testapp", 2000.0).await;
+    insert_notification(&db, 3, 1, "Newer", "Message 3", "com.example.testapp", 3000.0).await;
+
+    let notifications = db.notifications_since(1500).await.unwrap();
+    let titles: Vec<&str> = notifications.iter().map(|n| n.title.as_str()).collect();
+    assert_eq!(titles, vec!["New", "Newer"]);
+}
+
+#[tokio::test]
+async fn test_fixture_api_builds_a_readable_database() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    let data = create_test_plist_data("Title", "Body", "com.example.testapp", 1234567890.0);
+    let first = db.insert_test_notification(1, vec![0u8; 16], data.clone(), 0.0, 0.0, 0.0, true, 0, 0.0).await.unwrap();
+    let second = db.insert_test_notification(1, vec![0u8; 16], data, 0.0, 0.0, 0.0, true, 0, 0.0).await.unwrap();
+    assert_eq!((first, second), (1, 2));
+
+    let mut daemon = NotificationDaemon::new(&db_path);
+    daemon.run_once().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(2));
+}
+
+#[tokio::test]
+async fn test_daemon_skips_records_without_data() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Initial Notification", "Initial message", "com.example.testapp", 1234567890.0).await;
+
+    let mut daemon = NotificationDaemon::new(&db_path);
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(1));
+
+    // Insert records with NULL and empty data blobs
+    db.connect().await.unwrap()
+        .call(|db_conn| {
+            db_conn.execute("INSERT INTO record (rec_id, app_id, data) VALUES (2, 1, NULL)", [])?;
+            db_conn.execute("INSERT INTO record (rec_id, app_id, data) VALUES (3, 1, X'')", [])?;
+            Ok(())
+        }).await.unwrap();
+
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(3));
+}
+
+#[tokio::test]
+async fn test_daemon_monitors_multiple_databases() {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let (first_dir, first_db) = create_test_database().await;
+    let first_path = first_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    let (second_dir, second_db) = create_test_database().await;
+    let second_path = second_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&first_db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    insert_notification(&second_db, 5, 1, "Second", "Message 2", "com.example.testapp", 1234567891.0).await;
+
+    let mut daemon = NotificationDaemon::new(&first_path).with_database(&second_path);
+    daemon.check_for_new_notifications().await.unwrap();
+
+    // Each database tracks its own position
+    let last_rowids: Vec<Option<i64>> = daemon.sources().iter().map(|s| s.last_rowid).collect();
+    assert_eq!(last_rowids, vec![Some(1), Some(5)]);
+
+    // Notifications are tagged with the database they came from
+    let mut notifications = Box::pin(daemon.with_poll_interval(Duration::from_millis(50)).stream());
+    insert_notification(&second_db, 6, 1, "Third", "Message 3", "com.example.testapp", 1234567892.0).await;
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), notifications.next())
+        .await
+        .expect("timed out waiting for notification")
+        .expect("stream ended")
+        .expect("poll failed");
+    assert_eq!(notification.title, "Third");
+    assert_eq!(notification.source, Some(second_path));
+}
+
+#[tokio::test]
+async fn test_failing_database_does_not_stall_others() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    let missing_path = temp_dir.path().join("missing.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+
+    let mut daemon = NotificationDaemon::new(&missing_path).with_database(&db_path);
+    assert!(daemon.check_for_new_notifications().await.is_err());
+    assert_eq!(daemon.sources()[1].last_rowid, Some(1));
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1234567891.0).await;
+    db.connect().await.unwrap()
+        .call(|db_conn| {
+            db_conn.execute("INSERT INTO record (rec_id, app_id, data) VALUES (3, 1, X'00')", [])?;
+            Ok(())
+        }).await.unwrap();
+
+    let mut daemon = NotificationDaemon::new(&db_path);
+    daemon.run_once().await.unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(blurt::server::serve(listener, daemon.metrics(), daemon.recent(), std::time::Duration::from_secs(60)));
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("blurt_notifications_processed_total 2"));
+    assert!(response.contains("blurt_parse_failures_total 1"));
+    assert!(response.contains("blurt_notifications_by_bundle_total{bundle_id=\"com.example.testapp\"} 2"));
+
+    server.abort();
+}
+
+#[test]
+fn test_thread_id_parsing() {
+    use blurt::daemon::parse_notification_from_plist;
+    use plist::Value;
+
+    let mut req_dict = plist::Dictionary::new();
+    req_dict.insert("titl".to_string(), Value::String("Alice".to_string()));
+    req_dict.insert("thre".to_string(), Value::String("conversation-42".to_string()));
+
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.thread_id, Some("conversation-42".to_string()));
+}
+
+#[test]
+fn test_top_level_subtitle_parsing() {
+    use blurt::daemon::parse_notification_from_plist;
+    use plist::Value;
+
+    let mut req_dict = plist::Dictionary::new();
+    req_dict.insert("titl".to_string(), Value::String("Alice".to_string()));
+
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict.clone()));
+    main_dict.insert("subt".to_string(), Value::String("Project chat".to_string()));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict.clone()), 1).remove(0);
+    assert_eq!(notification.subtitle, Some("Project chat".to_string()));
+
+    // The nested subtitle wins when both are present
+    req_dict.insert("subt".to_string(), Value::String("Nested".to_string()));
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.subtitle, Some("Nested".to_string()));
+}
+
+#[test]
+fn test_category_parsing() {
+    use blurt::daemon::parse_notification_from_plist;
+    use plist::Value;
+
+    let mut req_dict = plist::Dictionary::new();
+    req_dict.insert("titl".to_string(), Value::String("Meeting".to_string()));
+    req_dict.insert("cate".to_string(), Value::String("com.apple.calendar.invitation".to_string()));
+
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict.clone()));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.category, Some("com.apple.calendar.invitation".to_string()));
+
+    // Missing categories are left empty
+    req_dict.remove("cate");
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.category, None);
+}
+
+#[test]
+fn test_interruption_level_parsing() {
+    use blurt::daemon::parse_notification_from_plist;
+    use blurt::database::InterruptionLevel;
+    use plist::Value;
+
+    let level_of = |level: Option<Value>| {
+        let mut req_dict = plist::Dictionary::new();
+        req_dict.insert("titl".to_string(), Value::String("Server down".to_string()));
+        if let Some(level) = level {
+            req_dict.insert("intl".to_string(), level);
+        }
+        let mut main_dict = plist::Dictionary::new();
+        main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+        parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0).interruption_level
+    };
+
+    assert_eq!(level_of(Some(Value::Integer(3.into()))), Some(InterruptionLevel::Critical));
+    assert_eq!(level_of(Some(Value::Integer(0.into()))), Some(InterruptionLevel::Passive));
+    assert_eq!(level_of(Some(Value::String("timeSensitive".to_string()))), Some(InterruptionLevel::TimeSensitive));
+    // Unknown and missing levels are left empty
+    assert_eq!(level_of(Some(Value::Integer(7.into()))), None);
+    assert_eq!(level_of(None), None);
+
+    let json = serde_json::to_value(InterruptionLevel::TimeSensitive).unwrap();
+    assert_eq!(json, "time-sensitive");
+}
+
+#[test]
+fn test_attributed_string_body_title_and_subtitle() {
+    use blurt::daemon::parse_notification_from_plist;
+    use plist::Value;
+
+    let mut attributes = plist::Dictionary::new();
+    attributes.insert("NSFont".to_string(), Value::String("Helvetica".to_string()));
+    let mut body = plist::Dictionary::new();
+    body.insert("NSAttributes".to_string(), Value::Dictionary(attributes));
+    body.insert("NSString".to_string(), Value::String("Your order has shipped".to_string()));
+
+    // The text can be nested another level down
+    let mut inner = plist::Dictionary::new();
+    inner.insert("NS.string".to_string(), Value::String("Order update".to_string()));
+    let mut title = plist::Dictionary::new();
+    title.insert("NSString".to_string(), Value::Dictionary(inner));
+    let mut subtitle = plist::Dictionary::new();
+    subtitle.insert("string".to_string(), Value::String("Arriving Tuesday".to_string()));
+
+    let mut req_dict = plist::Dictionary::new();
+    req_dict.insert("titl".to_string(), Value::Dictionary(title));
+    req_dict.insert("subt".to_string(), Value::Dictionary(subtitle));
+    req_dict.insert("body".to_string(), Value::Dictionary(body));
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.title, "Order update");
+    assert_eq!(notification.subtitle.as_deref(), Some("Arriving Tuesday"));
+    assert_eq!(notification.body, "Your order has shipped");
+
+    // A dictionary without any text leaves the body empty
+    let mut req_dict = plist::Dictionary::new();
+    req_dict.insert("body".to_string(), Value::Dictionary(plist::Dictionary::new()));
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.body, "");
+}
+
+#[test]
+fn test_array_record_parsing() {
+    use blurt::daemon::parse_notification_from_plist;
+    use plist::Value;
+
+    let entry = |title: &str| {
+        let mut req_dict = plist::Dictionary::new();
+        req_dict.insert("titl".to_string(), Value::String(title.to_string()));
+        let mut main_dict = plist::Dictionary::new();
+        main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+        Value::Dictionary(main_dict)
+    };
+
+    // Entries that aren't dictionaries are skipped
+    let record = Value::Array(vec![entry("First"), Value::Integer(1.into()), entry("Second")]);
+    let notifications = parse_notification_from_plist(&record, 7);
+    let titles: Vec<_> = notifications.iter().map(|n| (n.id, n.title.as_str())).collect();
+    assert_eq!(titles, vec![(7, "First"), (7, "Second")]);
+
+    assert!(parse_notification_from_plist(&Value::String("nope".to_string()), 8).is_empty());
+}
+
+#[test]
+fn test_action_parsing() {
+    use blurt::daemon::parse_notification_from_plist;
+    use blurt::database::Action;
+    use plist::Value;
+
+    let mut reply = plist::Dictionary::new();
+    reply.insert("titl".to_string(), Value::String("Reply".to_string()));
+    reply.insert("iden".to_string(), Value::String("com.example.reply".to_string()));
+
+    let mut req_dict = plist::Dictionary::new();
+    req_dict.insert("titl".to_string(), Value::String("Alice".to_string()));
+    req_dict.insert("acts".to_string(), Value::Array(vec![Value::Dictionary(reply)]));
+
+    let mut main_dict = plist::Dictionary::new();
+    main_dict.insert("req".to_string(), Value::Dictionary(req_dict));
+
+    let notification = parse_notification_from_plist(&Value::Dictionary(main_dict), 1).remove(0);
+    assert_eq!(notification.actions, vec![Action {
+        title: "Reply".to_string(),
+        identifier: "com.example.reply".to_string(),
+    }]);
+}
+
+#[tokio::test]
+async fn test_watch_picks_up_changes_before_next_poll() {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Initial Notification", "Initial message", "com.example.testapp", 1234567890.0).await;
+
+    // The poll interval is far longer than the timeout so only the watcher can deliver in time
+    let daemon = NotificationDaemon::new(&db_path)
+        .with_poll_interval(Duration::from_secs(60))
+        .with_watch(true);
+    let mut notifications = Box::pin(daemon.stream());
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    insert_notification(&db, 2, 1, "New Notification", "New message", "com.example.testapp", 1234567891.0).await;
+
+    let notification = tokio::time::timeout(Duration::from_secs(2), notifications.next())
+        .await
+        .expect("timed out waiting for notification")
+        .expect("stream ended")
+        .expect("poll failed");
+    assert_eq!(notification.id, 2);
+}
+
+#[tokio::test]
+async fn test_daemon_survives_database_being_replaced() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1234567891.0).await;
+
+    let mut daemon = NotificationDaemon::new(&db_path);
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(2));
+
+    // The OS deletes the database, checks fail until it comes back
+    std::fs::remove_file(&db_path).unwrap();
+    let result = daemon.check_for_new_notifications().await;
+    assert!(matches!(result, Err(blurt::error::BlurtError::DatabaseNotFound(_))));
+
+    // The rebuilt database starts its ROWIDs over
+    let (rebuilt_dir, rebuilt_db) = create_test_database().await;
+    insert_notification(&rebuilt_db, 1, 1, "Rebuilt", "Message 3", "com.example.testapp", 1234567892.0).await;
+    std::fs::rename(rebuilt_dir.path().join("notifications.db"), &db_path).unwrap();
+
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(daemon.last_rowid(), Some(1));
+    assert!(daemon.metrics().render().contains("blurt_notifications_processed_total 1"));
+}
+
+#[tokio::test]
+async fn test_notification_handlers_run_in_order() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1234567891.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let first = seen.clone();
+    let second = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| first.lock().unwrap().push(format!("a:{}", n.title)))
+        .on_notification(move |n| second.lock().unwrap().push(format!("b:{}", n.title)));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec!["a:First", "b:First", "a:Second", "b:Second"]);
+}
+
+#[tokio::test]
+async fn test_include_raw_attaches_plist() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_include_raw(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(serde_json::to_value(n).unwrap()));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[0]["raw"]["req"]["titl"], "First");
+    assert_eq!(seen[0]["raw"]["app"], "com.example.testapp");
+}
+
+#[tokio::test]
+async fn test_include_metadata_attaches_record_columns() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 42, 7, "First", "Message 1", "com.example.testapp", 1234567890.5).await;
+
+    for include_metadata in [true, false] {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler_seen = seen.clone();
+        let mut daemon = NotificationDaemon::new(&db_path)
+            .with_from_beginning(true)
+            .with_include_metadata(include_metadata)
+            .on_notification(move |n| handler_seen.lock().unwrap().push(serde_json::to_value(n).unwrap()));
+        daemon.check_for_new_notifications().await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        if include_metadata {
+            assert_eq!(seen[0]["metadata"], serde_json::json!({
+                "rec_id": 42,
+                "app_id": 7,
+                "presented": true,
+                "style": 0,
+                "delivered_date": 1234567890.5,
+            }));
+        } else {
+            // Left out entirely unless asked for
+            assert!(seen[0].get("metadata").is_none());
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_fields_limit_serialized_fields() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_fields(vec!["title".to_string(), "bundle_id".to_string()])
+        .on_notification(move |n| handler_seen.lock().unwrap().push(serde_json::to_value(n).unwrap()));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(seen.lock().unwrap()[0], serde_json::json!({
+        "title": "First",
+        "bundle_id": "com.example.testapp",
+    }));
+}
+
+#[tokio::test]
+async fn test_delivered_at_is_serialized_in_rfc3339() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(serde_json::to_value(n).unwrap()));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(seen.lock().unwrap()[0]["delivered_at"], "2040-02-14T23:31:30Z");
+}
+
+#[tokio::test]
+async fn test_first_record_in_empty_database_is_emitted() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(n.title.clone()));
+
+    // Without a backlog to skip, the first check only sets the baseline
+    daemon.check_for_new_notifications().await.unwrap();
+    assert!(seen.lock().unwrap().is_empty());
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec!["First"]);
+}
+
+#[tokio::test]
+async fn test_statsd_sink_counts_by_bundle_and_times_polls() {
+    use blurt::sink::StatsdSink;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1234567890.0).await;
+
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    server.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+    let sink = StatsdSink::new(&server.local_addr().unwrap().to_string(), "blurt").unwrap();
+
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_sink(Box::new(sink));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    let mut metrics = Vec::new();
+    let mut buf = [0; 512];
+    for _ in 0..2 {
+        let len = server.recv(&mut buf).unwrap();
+        metrics.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+    }
+    assert_eq!(metrics[0], "blurt.notifications.com.example.testapp:1|c");
+    assert!(metrics[1].starts_with("blurt.poll:") && metrics[1].ends_with("|ms"), "{}", metrics[1]);
+}
+
+#[tokio::test]
+async fn test_sse_sink_resumes_after_last_event_id() {
+    use blurt::sink::SseSink;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    // Both entries of an array record share a ROWID but not an event id
+    let mut entries = Vec::new();
+    for title in ["First", "Second"] {
+        let data = create_test_plist_data(title, "Message", "com.example.testapp", 1234567890.0);
+        entries.push(plist::from_bytes::<plist::Value>(&data).unwrap());
+    }
+    let mut data = Vec::new();
+    plist::to_writer_binary(&mut data, &plist::Value::Array(entries)).unwrap();
+    db.insert_test_notification(1, vec![0u8; 16], data, 0.0, 0.0, 1234567890.0, true, 0, 0.0).await.unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let daemon = NotificationDaemon::new(&db_path).with_from_beginning(true);
+    let sink = SseSink::new(listener, daemon.recent());
+    let mut daemon = daemon.with_sink(Box::new(sink));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\nlast-event-id: 1\r\n\r\n").await.unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    assert_eq!(lines.next_line().await.unwrap().unwrap(), "HTTP/1.1 200 OK");
+
+    // The missed notification is replayed before live ones
+    insert_notification(&db, 2, 1, "Third", "Message", "com.example.testapp", 1234567891.0).await;
+    daemon.check_for_new_notifications().await.unwrap();
+    let mut events = Vec::new();
+    let mut id = String::new();
+    while events.len() < 2 {
+        let line = tokio::time::timeout(std::time::Duration::from_secs(5), lines.next_line()).await.unwrap().unwrap().unwrap();
+        if let Some(event_id) = line.strip_prefix("id: ") {
+            id = event_id.to_string();
+        } else if let Some(data) = line.strip_prefix("data: ") {
+            let notification: serde_json::Value = serde_json::from_str(data).unwrap();
+            events.push((id.clone(), notification["title"].as_str().unwrap().to_string()));
+        }
+    }
+    assert_eq!(events, vec![
+        ("2".to_string(), "Second".to_string()),
+        ("3".to_string(), "Third".to_string()),
+    ]);
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_websocket_sink_backfills_recent_notifications() {
+    use blurt::sink::WebSocketSink;
+    use futures::StreamExt;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    for (rowid, title) in [(1, "First"), (2, "Second")] {
+        insert_notification(&db, rowid, 1, title, "Message", "com.example.testapp", 1234567890.0).await;
+    }
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let daemon = NotificationDaemon::new(&db_path).with_from_beginning(true);
+    let sink = WebSocketSink::new(listener, daemon.recent());
+    let mut daemon = daemon.with_sink(Box::new(sink));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/?backfill=1", addr)).await.unwrap();
+    insert_notification(&db, 3, 1, "Third", "Message", "com.example.testapp", 1234567891.0).await;
+    daemon.check_for_new_notifications().await.unwrap();
+
+    let mut titles = Vec::new();
+    while titles.len() < 2 {
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next()).await.unwrap().unwrap().unwrap();
+        let notification: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        titles.push(notification["title"].as_str().unwrap().to_string());
+    }
+    assert_eq!(titles, vec!["Second", "Third"]);
+}
+
+#[tokio::test]
+async fn test_app_counts_sorted_by_noisiest() {
+    let (_temp_dir, db) = create_test_database().await;
+
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.quiet", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.noisy", 1234567891.0).await;
+    insert_notification(&db, 3, 1, "Third", "Message 3", "com.example.noisy", 1234567892.0).await;
+
+    let counts = blurt::stats::app_counts(std::slice::from_ref(&db)).await.unwrap();
+    let counts: Vec<_> = counts.iter()
+        .map(|app_count| (app_count.bundle_id.as_deref().unwrap(), app_count.count))
+        .collect();
+    assert_eq!(counts, vec![("com.example.noisy", 2), ("com.example.quiet", 1)]);
+}
+
+#[test]
+fn test_truncate_body_keeps_characters_whole() {
+    use blurt::database::Notification;
+
+    let mut notification = Notification {
+        body: "Café ☕ déjà vu".to_string(),
+        ..Default::default()
+    };
+    notification.truncate_body(6);
+    assert_eq!(notification.body, "Café …");
+
+    // Bodies within the limit are left alone
+    let mut short = Notification {
+        body: "Café".to_string(),
+        ..Default::default()
+    };
+    short.truncate_body(4);
+    assert_eq!(short.body, "Café");
+}
+
+#[tokio::test]
+async fn test_patterns_filter_by_title_or_body() {
+    use regex::Regex;
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Build failed", "main is red", "com.example.ci", 1234567890.0).await;
+    insert_notification(&db, 2, 1, "Messages", "Your OTP is 1234", "com.example.sms", 1234567891.0).await;
+    insert_notification(&db, 3, 1, "Messages", "Lunch?", "com.example.sms", 1234567892.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_title_patterns(vec![Regex::new("(?i)build failed").unwrap()])
+        .with_body_patterns(vec![Regex::new("OTP").unwrap()])
+        .on_notification(move |n| handler_seen.lock().unwrap().push(n.id));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+}
+
+#[tokio::test]
+async fn test_delivered_date_takes_precedence_over_plist_date() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    let data = create_test_plist_data("Differs", "Message 1", "com.example.testapp", 1000.0);
+    db.insert_test_notification(1, vec![0u8; 16], data, 1000.0, 1000.0, 2000.0, true, 0, 0.0).await.unwrap();
+
+    // Without a delivered date the plist's date is used
+    let data = create_test_plist_data("Missing", "Message 2", "com.example.testapp", 3000.0);
+    db.connect().await.unwrap()
+        .call(move |db_conn| {
+            db_conn.execute("INSERT INTO record (app_id, data, delivered_date) VALUES (1, ?, NULL)", [data])?;
+            Ok(())
+        }).await.unwrap();
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(n.date));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec![2000, 3000]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket_sink_holds_notifications_until_listener_is_up() {
+    use blurt::database::Notification;
+    use blurt::sink::{NotificationSink, UnixSocketSink};
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::UnixListener;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("blurt.sock");
+
+    // Nothing is listening yet so the notification has to wait
+    let sink = UnixSocketSink::new(&path);
+    let notification = Notification {
+        id: 1,
+        title: "Title".to_string(),
+        body: "Body".to_string(),
+        ..Default::default()
+    };
+    sink.handle(&notification).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    let listener = UnixListener::bind(&path).unwrap();
+    let (stream, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept())
+        .await
+        .expect("timed out waiting for the sink to connect")
+        .unwrap();
+
+    let mut lines = BufReader::new(stream).lines();
+    let line = lines.next_line().await.unwrap().unwrap();
+    assert_eq!(line, serde_json::to_string(&notification).unwrap());
+}
+
+#[tokio::test]
+async fn test_monitoring_survives_transient_errors() {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Initial Notification", "Initial message", "com.example.testapp", 1234567890.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_poll_interval(Duration::from_millis(50))
+        .with_max_consecutive_errors(5)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(n.title.clone()));
+    let monitor = tokio::spawn(async move { daemon.start().await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Checks fail while the database is missing
+    std::fs::remove_file(&db_path).unwrap();
+    tokio::time::sleep(Duration::from_millis(75)).await;
+
+    let (rebuilt_dir, rebuilt_db) = create_test_database().await;
+    insert_notification(&rebuilt_db, 1, 1, "After Error", "Message", "com.example.testapp", 1234567891.0).await;
+    std::fs::rename(rebuilt_dir.path().join("notifications.db"), &db_path).unwrap();
+
+    // The loop kept going and picked up the notification once the database was back
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while seen.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }).await.expect("timed out waiting for notification");
+    assert_eq!(*seen.lock().unwrap(), vec!["After Error"]);
+
+    // Errors that don't go away eventually stop monitoring
+    std::fs::remove_file(&db_path).unwrap();
+    let result = tokio::time::timeout(Duration::from_secs(5), monitor)
+        .await
+        .expect("monitoring didn't give up")
+        .unwrap();
+    assert!(matches!(result, Err(blurt::error::BlurtError::DatabaseNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_uuid_parsed_from_record() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    let uuid = vec![0x12, 0x3e, 0x45, 0x67, 0xe8, 0x9b, 0x12, 0xd3, 0xa4, 0x56, 0x42, 0x66, 0x14, 0x17, 0x40, 0x00];
+    let data = create_test_plist_data("With uuid", "Message 1", "com.example.testapp", 1000.0);
+    db.insert_test_notification(1, uuid, data, 1000.0, 1000.0, 1000.0, true, 0, 0.0).await.unwrap();
+
+    // Malformed blobs are left out rather than guessed at
+    let data = create_test_plist_data("Short uuid", "Message 2", "com.example.testapp", 1000.0);
+    db.insert_test_notification(1, vec![1, 2, 3], data, 1000.0, 1000.0, 1000.0, true, 0, 0.0).await.unwrap();
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push(n.uuid.clone()));
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec![Some("123e4567-e89b-12d3-a456-426614174000".to_string()), None]);
+    assert_eq!(db.all_notifications().await.unwrap()[0].uuid.as_deref(), Some("123e4567-e89b-12d3-a456-426614174000"));
+}
+
+#[tokio::test]
+async fn test_delivered_only_skips_unpresented_notifications() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    let data = create_test_plist_data("Shown", "Message 1", "com.example.testapp", 1000.0);
+    db.insert_test_notification(1, vec![0u8; 16], data, 1000.0, 1000.0, 1000.0, true, 0, 0.0).await.unwrap();
+    let data = create_test_plist_data("Snoozed", "Message 2", "com.example.testapp", 1000.0);
+    db.insert_test_notification(1, vec![0u8; 16], data, 1000.0, 1000.0, 1000.0, false, 0, 5000.0).await.unwrap();
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push((n.title.clone(), n.presented, n.snoozed)));
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec![
+        ("Shown".to_string(), true, false),
+        ("Snoozed".to_string(), false, true),
+    ]);
+
+    seen.lock().unwrap().clear();
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_delivered_only(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push((n.title.clone(), n.presented, n.snoozed)));
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec![("Shown".to_string(), true, false)]);
+}
+
+#[tokio::test]
+async fn test_export_quotes_csv_fields() {
+    let (_temp_dir, db) = create_test_database().await;
+    insert_notification(&db, 1, 1, "Lunch, then coffee", "He said \"hi\"\nthen left", "com.example.testapp", 1000.0).await;
+    insert_notification(&db, 2, 1, "Plain", "Nothing special", "com.example.testapp", 2000.0).await;
+
+    let notifications = blurt::export::read_notifications(std::slice::from_ref(&db), Some(500)).await.unwrap();
+    assert_eq!(notifications.len(), 2);
+
+    let mut csv = Vec::new();
+    blurt::export::write_csv(&mut csv, &notifications).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let rows: Vec<&str> = csv.split("\r\n").collect();
+    assert_eq!(rows[0], "id,uuid,date,bundle_id,app_name,title,subtitle,body,thread_id,source");
+    assert!(rows[1].contains(",\"Lunch, then coffee\",,\"He said \"\"hi\"\"\nthen left\","));
+    assert!(rows[2].starts_with("2,00000000-0000-0000-0000-000000000000,2000,com.example.testapp,,Plain,,Nothing special,,"));
+
+    let mut ndjson = Vec::new();
+    blurt::export::write_ndjson(&mut ndjson, &notifications[1..]).unwrap();
+    let line: serde_json::Value = serde_json::from_slice(&ndjson).unwrap();
+    assert_eq!(line["title"], "Plain");
+
+    let since = blurt::export::read_notifications(std::slice::from_ref(&db), Some(1500)).await.unwrap();
+    assert_eq!(since.len(), 1);
+}
+
+#[tokio::test]
+async fn test_redact_and_hash_body() {
+    use std::sync::{Arc, Mutex};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    insert_notification(&db, 1, 1, "Code 123456", "Your code is 123456, mail me@example.com", "com.example.testapp", 1000.0).await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_redact_patterns(vec![regex::Regex::new(r"\d{6}").unwrap(), regex::Regex::new(r"\S+@\S+").unwrap()])
+        .on_notification(move |n| handler_seen.lock().unwrap().push((n.title.clone(), n.body.clone())));
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec![("Code 123456".to_string(), "Your code is ***, mail ***".to_string())]);
+
+    seen.lock().unwrap().clear();
+    let handler_seen = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_hash_body(true)
+        .with_redact_title(true)
+        .on_notification(move |n| handler_seen.lock().unwrap().push((n.title.clone(), n.body.clone())));
+    daemon.check_for_new_notifications().await.unwrap();
+    let (title, body) = seen.lock().unwrap()[0].clone();
+    assert_eq!(body.len(), 64);
+    assert!(body.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(title, "Code 123456");
+}
+
+#[tokio::test]
+async fn test_health_endpoint_reports_stalled_polling() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1000.0).await;
+
+    let mut daemon = NotificationDaemon::new(&db_path);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(blurt::server::serve(listener, daemon.metrics(), daemon.recent(), std::time::Duration::from_secs(60)));
+
+    // Nothing has been polled yet
+    assert!(get(addr, "/health").await.starts_with("HTTP/1.1 503"));
+    assert!(get(addr, "/ready").await.starts_with("HTTP/1.1 503"));
+
+    daemon.check_for_new_notifications().await.unwrap();
+    let response = get(addr, "/health").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    let body: serde_json::Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["healthy"], true);
+    assert_eq!(body["last_rowid"][&db_path], 1);
+    assert_eq!(body["consecutive_errors"], 0);
+    assert!(get(addr, "/ready").await.starts_with("HTTP/1.1 200 OK"));
+    // The first check only sets the baseline so nothing was dispatched
+    assert!(get(addr, "/recent").await.ends_with("\r\n\r\n[]"));
+
+    std::fs::remove_file(&db_path).unwrap();
+    assert!(daemon.check_for_new_notifications().await.is_err());
+    let response = get(addr, "/health").await;
+    let body: serde_json::Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["consecutive_errors"], 1);
+
+    // Polling that stopped succeeding longer ago than allowed is unhealthy
+    assert!(body["last_poll"].is_number());
+    assert!(!daemon.metrics().health(std::time::Duration::ZERO).healthy);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_incompatible_schema_names_missing_column() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    let db = blurt::database::NotificationDatabase::new_with_mode(&db_path, false);
+    db.connect().await.unwrap()
+        .call(|db_conn| {
+            db_conn.execute_batch(
+                "CREATE TABLE app (app_id INTEGER PRIMARY KEY, identifier VARCHAR);
+                 CREATE TABLE record (rec_id INTEGER PRIMARY KEY, app_id INTEGER, uuid BLOB, payload BLOB,
+                   delivered_date REAL, presented Bool, snooze_fire_date REAL);"
+            )?;
+            Ok(())
+        }).await.unwrap();
+
+    let mut daemon = NotificationDaemon::new(&db_path);
+    let err = daemon.check_for_new_notifications().await.unwrap_err();
+    assert!(matches!(&err, blurt::error::BlurtError::IncompatibleSchema { column, .. } if column == "data"));
+    assert!(err.to_string().contains("no data column"));
+
+    // Watching updates also reads when records were last requested
+    db.connect().await.unwrap()
+        .call(|db_conn| {
+            db_conn.execute_batch("ALTER TABLE record ADD COLUMN data BLOB")?;
+            Ok(())
+        }).await.unwrap();
+    let mut daemon = NotificationDaemon::new(&db_path);
+    daemon.check_for_new_notifications().await.unwrap();
+    let mut daemon = NotificationDaemon::new(&db_path).with_watch_updates(true);
+    let err = daemon.check_for_new_notifications().await.unwrap_err();
+    assert!(matches!(&err, blurt::error::BlurtError::IncompatibleSchema { column, .. } if column == "request_last_date"));
+}
+
+#[tokio::test]
+async fn test_start_rejects_database_without_record_table() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("empty.db").to_str().unwrap().to_string();
+    rusqlite::Connection::open(&db_path).unwrap()
+        .execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, text TEXT)")
+        .unwrap();
+
+    // Returns instead of polling forever
+    let mut daemon = NotificationDaemon::new(&db_path).with_immutable(true);
+    let err = daemon.start().await.unwrap_err();
+    assert!(matches!(&err, blurt::error::BlurtError::IncompatibleDatabase(path) if *path == db_path));
+    assert!(err.to_string().contains("doesn't look like a macOS notification database"));
+}
+
+#[tokio::test]
+async fn test_batching_hands_a_check_to_the_sink_at_once() {
+    use async_trait::async_trait;
+    use blurt::database::Notification;
+    use blurt::error::BlurtError;
+    use blurt::sink::NotificationSink;
+    use std::sync::{Arc, Mutex};
+
+    struct RecordingSink {
+        batches: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    #[async_trait]
+    impl NotificationSink for RecordingSink {
+        async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+            self.batches.lock().unwrap().push(vec![notification.title.clone()]);
+            Ok(())
+        }
+
+        async fn handle_batch(&self, notifications: &[Notification]) -> Result<(), BlurtError> {
+            self.batches.lock().unwrap().push(notifications.iter().map(|n| n.title.clone()).collect());
+            Ok(())
+        }
+    }
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1000.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1001.0).await;
+
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_sink(Box::new(RecordingSink { batches: batches.clone() }))
+        .with_from_beginning(true)
+        .with_batching(true);
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*batches.lock().unwrap(), vec![vec!["First".to_string(), "Second".to_string()]]);
+
+    // Checks that find nothing don't dispatch an empty batch
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(batches.lock().unwrap().len(), 1);
+
+    batches.lock().unwrap().clear();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_sink(Box::new(RecordingSink { batches: batches.clone() }))
+        .with_from_beginning(true);
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*batches.lock().unwrap(), vec![vec!["First".to_string()], vec!["Second".to_string()]]);
+}
+
+#[tokio::test]
+async fn test_wal_mode_sees_rows_not_yet_checkpointed() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Checkpointed", "In the main file", "com.example.testapp", 1234567890.0).await;
+
+    // Keep the writer open with checkpoints off so the new row stays in the WAL
+    let writer = rusqlite::Connection::open(&db_path).unwrap();
+    let _: String = writer.query_row("PRAGMA journal_mode=WAL", [], |row| row.get(0)).unwrap();
+    writer.execute_batch("PRAGMA wal_autocheckpoint=0").unwrap();
+    writer.execute(
+        "INSERT INTO record (rec_id, app_id, uuid, data, request_date, request_last_date,
+          delivered_date, presented, style, snooze_fire_date)
+         VALUES (2, 1, ?, ?, 0, 0, 0, 1, 0, 0)",
+        rusqlite::params![vec![0u8; 16], create_test_plist_data("In the WAL", "Not checkpointed", "com.example.testapp", 1234567891.0)],
+    ).unwrap();
+
+    for (immutable, expected) in [(true, vec!["Checkpointed"]), (false, vec!["Checkpointed", "In the WAL"])] {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let mut daemon = NotificationDaemon::new(&db_path)
+            .with_immutable(immutable)
+            .with_from_beginning(true)
+            .on_notification(move |n| seen_clone.lock().unwrap().push(n.title.clone()));
+
+        daemon.check_for_new_notifications().await.unwrap();
+        assert_eq!(*seen.lock().unwrap(), expected, "immutable: {}", immutable);
+    }
+
+    drop(writer);
+}
+
+#[tokio::test]
+async fn test_parse_error_handler_gets_raw_data() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    insert_notification(&db, 1, 1, "Parsed", "Fine", "com.example.testapp", 1234567890.0).await;
+    // Not a plist at all, and a plist that isn't a notification
+    let mut not_a_notification = Vec::new();
+    plist::to_writer_binary(&mut not_a_notification, &plist::Value::String("hello".to_string())).unwrap();
+    for (rowid, data) in [(2i64, b"not a plist".to_vec()), (3, not_a_notification.clone())] {
+        let inserted = db.insert_test_notification(1, vec![0u8; 16], data, 0.0, 0.0, 0.0, true, 0, 0.0).await.unwrap();
+        assert_eq!(inserted, rowid);
+    }
+
+    let failures = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let failures_clone = failures.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_parse_error(move |rowid, data| failures_clone.lock().unwrap().push((rowid, data.to_vec())));
+
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*failures.lock().unwrap(), vec![(2, b"not a plist".to_vec()), (3, not_a_notification)]);
+    assert_eq!(daemon.last_rowid(), Some(3));
+}
+
+#[tokio::test]
+async fn test_gzipped_plist_is_parsed() {
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&create_test_plist_data("Compressed", "Smaller", "com.example.testapp", 1234567890.0)).unwrap();
+    let data = encoder.finish().unwrap();
+    db.insert_test_notification(1, vec![0u8; 16], data, 0.0, 0.0, 1234567890.0, true, 0, 0.0).await.unwrap();
+    insert_notification(&db, 2, 1, "Plain", "As is", "com.example.testapp", 1234567891.0).await;
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| seen_clone.lock().unwrap().push((n.title.clone(), n.body.clone())));
+
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec![
+        ("Compressed".to_string(), "Smaller".to_string()),
+        ("Plain".to_string(), "As is".to_string()),
+    ]);
+    assert!(daemon.metrics().render().contains("blurt_parse_failures_total 0"));
+}
+
+#[tokio::test]
+async fn test_plist_nested_in_data_is_parsed() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    // The payload is encoded again as data in an outer plist
+    let inner = create_test_plist_data("Nested", "Twice encoded", "com.example.testapp", 1234567890.0);
+    let mut data = Vec::new();
+    plist::to_writer_binary(&mut data, &plist::Value::Data(inner)).unwrap();
+    db.insert_test_notification(1, vec![0u8; 16], data, 0.0, 0.0, 1234567890.0, true, 0, 0.0).await.unwrap();
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .on_notification(move |n| seen_clone.lock().unwrap().push((n.title.clone(), n.bundle_id.clone())));
+
+    daemon.check_for_new_notifications().await.unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), vec![("Nested".to_string(), Some("com.example.testapp".to_string()))]);
+    assert!(daemon.metrics().render().contains("blurt_parse_failures_total 0"));
+}
+
+#[tokio::test]
+async fn test_failing_sink_does_not_block_others() {
+    use async_trait::async_trait;
+    use blurt::database::Notification;
+    use blurt::error::BlurtError;
+    use blurt::sink::NotificationSink;
+    use std::sync::{Arc, Mutex};
+
+    struct FailingSink;
+
+    #[async_trait]
+    impl NotificationSink for FailingSink {
+        async fn handle(&self, _notification: &Notification) -> Result<(), BlurtError> {
+            Err(BlurtError::Sink("webhook is down".to_string()))
+        }
+    }
+
+    struct RecordingSink {
+        titles: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl NotificationSink for RecordingSink {
+        async fn handle(&self, notification: &Notification) -> Result<(), BlurtError> {
+            self.titles.lock().unwrap().push(notification.title.clone());
+            Ok(())
+        }
+    }
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    insert_notification(&db, 1, 1, "First", "Message 1", "com.example.testapp", 1000.0).await;
+    insert_notification(&db, 2, 1, "Second", "Message 2", "com.example.testapp", 1001.0).await;
+
+    for batch in [false, true] {
+        let titles = Arc::new(Mutex::new(Vec::new()));
+        let mut daemon = NotificationDaemon::new(&db_path)
+            .with_sinks(vec![
+                Box::new(FailingSink),
+                Box::new(RecordingSink { titles: titles.clone() }),
+            ])
+            .with_from_beginning(true)
+            .with_batching(batch);
+
+        // The failure is logged rather than failing the check
+        daemon.check_for_new_notifications().await.unwrap();
+        assert_eq!(*titles.lock().unwrap(), vec!["First".to_string(), "Second".to_string()], "batch: {}", batch);
+        assert_eq!(daemon.last_rowid(), Some(2));
+    }
+}
+
+#[tokio::test]
+async fn test_start_rowid_replays_later_records() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    for rowid in 1..=3 {
+        insert_notification(&db, rowid, 1, &format!("Notification {}", rowid), "Message", "com.example.testapp", 1000.0).await;
+    }
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_start_rowid(1)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(n.id));
+
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec![2, 3]);
+    assert_eq!(daemon.last_rowid(), Some(3));
+}
+
+#[test]
+fn test_notification_summary_and_display() {
+    use blurt::database::{Action, Notification};
+
+    let mut notification = Notification {
+        id: 1,
+        title: "Alice".to_string(),
+        body: "On my way".to_string(),
+        date: 0,
+        bundle_id: Some("com.apple.MobileSMS".to_string()),
+        actions: vec![Action { title: "Reply".to_string(), identifier: "reply".to_string() }],
+        ..Default::default()
+    };
+    assert_eq!(notification.summary(), "[com.apple.MobileSMS] Alice — On my way");
+
+    assert_eq!(notification.to_string(), [
+        "title: Alice",
+        "body: On my way",
+        "date: 2001-01-01 00:00:00 UTC",
+        "bundle_id: com.apple.MobileSMS",
+        "action: Reply (reply)",
+    ].join("\n"));
+
+    // The app's name wins over its bundle identifier and empty bodies are left out
+    notification.app_name = Some("Messages".to_string());
+    notification.body = String::new();
+    assert_eq!(notification.summary(), "[Messages] Alice");
+}
+
+#[tokio::test]
+async fn test_daemon_reads_in_memory_database() {
+    use blurt::database::NotificationDatabase;
+
+    let db = NotificationDatabase::new_in_memory().await.unwrap();
+    let data = create_test_plist_data("In memory", "No file needed", "com.example.testapp", 1234567890.0);
+    db.insert_test_notification(1, vec![0u8; 16], data, 0.0, 0.0, 0.0, true, 0, 0.0).await.unwrap();
+
+    // Each in-memory database is separate from the others
+    let other = NotificationDatabase::new_in_memory().await.unwrap();
+    assert!(other.all_notifications().await.unwrap().is_empty());
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::from_database(db)
+        .with_from_beginning(true)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(n.title.clone()));
+
+    daemon.run_once().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec!["In memory".to_string()]);
+}
+
+#[tokio::test]
+async fn test_start_stops_at_limits() {
+    use std::time::Duration;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    for rowid in 1..=3 {
+        insert_notification(&db, rowid, 1, &format!("Notification {}", rowid), "Message", "com.example.testapp", 1000.0).await;
+    }
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_max_notifications(2)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(n.id));
+    tokio::time::timeout(Duration::from_secs(5), daemon.start()).await.unwrap().unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+    assert_eq!(daemon.dispatched_count(), 2);
+
+    // Nothing new arrives so only the runtime ends it
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_max_runtime(Duration::from_millis(100));
+    tokio::time::timeout(Duration::from_secs(5), daemon.start()).await.unwrap().unwrap();
+    assert_eq!(daemon.dispatched_count(), 0);
+}
+
+#[tokio::test]
+async fn test_queued_notifications_count_towards_max_notifications() {
+    use blurt::schedule::QuietHours;
+    use chrono::{Local, TimeDelta};
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    for rowid in 1..=3 {
+        insert_notification(&db, rowid, 1, &format!("Notification {}", rowid), "Message", "com.example.testapp", 1000.0).await;
+    }
+
+    let now = Local::now().time();
+    let hours = |start: i64, end: i64| QuietHours::new(now + TimeDelta::hours(start), now + TimeDelta::hours(end));
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_quiet_hours(hours(-1, 1), true)
+        .with_max_notifications(2)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(n.id));
+    daemon.check_for_new_notifications().await.unwrap();
+    assert!(seen.lock().unwrap().is_empty());
+
+    // Quiet hours are over
+    let mut daemon = daemon.with_quiet_hours(hours(1, 2), true);
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+    assert_eq!(daemon.dispatched_count(), 2);
+}
+
+#[tokio::test]
+async fn test_schema_version_is_reported_in_health() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+
+    let conn = db.connect().await.unwrap();
+    assert_eq!(db.schema_version(&conn).await.unwrap(), 0);
+    conn.call(|db_conn| {
+        db_conn.execute_batch("PRAGMA user_version = 12")?;
+        Ok(())
+    }).await.unwrap();
+    assert_eq!(db.schema_version(&conn).await.unwrap(), 12);
+
+    let mut daemon = NotificationDaemon::new(&db_path).with_tested_schema_versions(vec![11]);
+    assert!(daemon.metrics().health(std::time::Duration::from_secs(60)).schema_version.is_empty());
+
+    // An untested version is only warned about
+    daemon.check_for_new_notifications().await.unwrap();
+    let health = daemon.metrics().health(std::time::Duration::from_secs(60));
+    assert_eq!(health.schema_version.get(&db_path), Some(&12));
+}
+
+#[tokio::test]
+async fn test_snapshot_is_read_and_removed() {
+    use blurt::database::NotificationDatabase;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    insert_notification(&db, 1, 1, "First", "Before the snapshot", "com.example.testapp", 1234567890.0).await;
+
+    let snapshot = NotificationDatabase::new(&db_path).with_snapshot(true);
+    let snapshot_path = snapshot.snapshot_path().unwrap().to_path_buf();
+    assert!(!snapshot_path.starts_with(temp_dir.path()));
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::from_database(snapshot)
+        .with_from_beginning(true)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(n.title.clone()));
+
+    daemon.check_for_new_notifications().await.unwrap();
+    assert!(snapshot_path.exists());
+    assert_eq!(*seen.lock().unwrap(), vec!["First".to_string()]);
+
+    // The copy is refreshed on every check
+    insert_notification(&db, 2, 1, "Second", "After the snapshot", "com.example.testapp", 1234567891.0).await;
+    daemon.check_for_new_notifications().await.unwrap();
+    assert_eq!(*seen.lock().unwrap(), vec!["First".to_string(), "Second".to_string()]);
+
+    drop(daemon);
+    assert!(!snapshot_path.parent().unwrap().exists());
+}
+
+#[tokio::test]
+async fn test_seen_uuids_persist_across_restarts() {
+    use blurt::seen::SeenUuids;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    let seen_path = temp_dir.path().join("seen.json");
+    for uuid in [1u8, 2] {
+        let data = create_test_plist_data(&format!("Notification {}", uuid), "Message", "com.example.testapp", 1000.0);
+        db.insert_test_notification(1, vec![uuid; 16], data, 0.0, 0.0, 0.0, true, 0, 0.0).await.unwrap();
+    }
+
+    let run = || async {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let mut daemon = NotificationDaemon::new(&db_path)
+            .with_from_beginning(true)
+            .with_seen_uuids(SeenUuids::load(&seen_path, 2).unwrap())
+            .on_notification(move |n| seen_clone.lock().unwrap().push(n.title.clone()));
+        daemon.check_for_new_notifications().await.unwrap();
+        seen.lock().unwrap().clone()
+    };
+
+    assert_eq!(run().await, vec!["Notification 1", "Notification 2"]);
+    // Replaying the backlog after a restart skips what was already emitted
+    assert!(run().await.is_empty());
+
+    // Only the most recent UUIDs are kept, so the oldest is forgotten
+    let data = create_test_plist_data("Notification 3", "Message", "com.example.testapp", 1000.0);
+    db.insert_test_notification(1, vec![3u8; 16], data, 0.0, 0.0, 0.0, true, 0, 0.0).await.unwrap();
+    assert_eq!(run().await, vec!["Notification 3"]);
+    let saved: Vec<String> = serde_json::from_slice(&std::fs::read(&seen_path).unwrap()).unwrap();
+    assert_eq!(saved, vec![
+        "02020202-0202-0202-0202-020202020202".to_string(),
+        "03030303-0303-0303-0303-030303030303".to_string(),
+    ]);
+}
+
+#[tokio::test]
+async fn test_dropped_records_are_not_remembered_as_seen() {
+    use blurt::seen::SeenUuids;
+
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    let seen_path = temp_dir.path().join("seen.json");
+    let data = create_test_plist_data("Filtered", "Message", "com.example.testapp", 1000.0);
+    db.insert_test_notification(1, vec![1u8; 16], data, 0.0, 0.0, 0.0, true, 0, 0.0).await.unwrap();
+
+    let run = |blocklist: Vec<String>| async {
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_clone = seen.clone();
+        let mut daemon = NotificationDaemon::new(&db_path)
+            .with_from_beginning(true)
+            .with_blocklist(blocklist)
+            .with_seen_uuids(SeenUuids::load(&seen_path, 10).unwrap())
+            .on_notification(move |n| seen_clone.lock().unwrap().push(n.title.clone()));
+        daemon.check_for_new_notifications().await.unwrap();
+        seen.lock().unwrap().clone()
+    };
+
+    // Dropped by the blocklist, so it's still emitted once that changes
+    assert!(run(vec!["com.example.testapp".to_string()]).await.is_empty());
+    assert_eq!(run(Vec::new()).await, vec!["Filtered"]);
+    assert!(run(Vec::new()).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_concurrent_inserts_are_read_once() {
+    let (temp_dir, _db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    const COUNT: i64 = 200;
+
+    // Insert while the daemon is checking so some land between
+    // reading the max ROWID and querying the new records
+    let writer_path = db_path.clone();
+    let writer = std::thread::spawn(move || {
+        let conn = rusqlite::Connection::open(&writer_path).unwrap();
+        conn.busy_timeout(std::time::Duration::from_secs(5)).unwrap();
+        for rowid in 1..=COUNT {
+            conn.execute(
+                "INSERT INTO record (rec_id, app_id, uuid, data, request_date, request_last_date,
+                  delivered_date, presented, style, snooze_fire_date)
+                 VALUES (?, 1, ?, ?, 0, 0, 0, 1, 0, 0)",
+                rusqlite::params![rowid, vec![0u8; 16], create_test_plist_data(&rowid.to_string(), "Message", "com.example.testapp", 1000.0)],
+            ).unwrap();
+            std::thread::sleep(std::time::Duration::from_micros(200));
+        }
+    });
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_dedup_window(std::time::Duration::ZERO)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(n.id));
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while seen.lock().unwrap().len() < COUNT as usize && std::time::Instant::now() < deadline {
+        daemon.check_for_new_notifications().await.unwrap();
+    }
+    writer.join().unwrap();
+    daemon.check_for_new_notifications().await.unwrap();
+
+    // Every record exactly once, in order
+    assert_eq!(*seen.lock().unwrap(), (1..=COUNT).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_watch_updates_emits_records_updated_in_place() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    insert_notification(&db, 1, 1, "Downloading", "10%", "com.example.testapp", 1000.0).await;
+    insert_notification(&db, 2, 1, "Other", "Unchanged", "com.example.testapp", 1000.0).await;
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_from_beginning(true)
+        .with_watch_updates(true)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(serde_json::to_value(n).unwrap()));
+
+    daemon.check_for_new_notifications().await.unwrap();
+    let events: Vec<_> = seen.lock().unwrap().iter().map(|n| n["event"].clone()).collect();
+    assert_eq!(events, vec!["new", "new"]);
+
+    // macOS rewrites the record with the new progress and date
+    let data = create_test_plist_data("Downloading", "55%", "com.example.testapp", 1005.0);
+    db.connect().await.unwrap()
+        .call(move |conn| {
+            conn.execute("UPDATE record SET data = ?, request_last_date = 1005 WHERE ROWID = 1", [data])?;
+            Ok(())
+        }).await.unwrap();
+
+    seen.lock().unwrap().clear();
+    daemon.check_for_new_notifications().await.unwrap();
+    let seen_now = seen.lock().unwrap().clone();
+    assert_eq!(seen_now.len(), 1);
+    assert_eq!(seen_now[0]["id"], 1);
+    assert_eq!(seen_now[0]["body"], "55%");
+    assert_eq!(seen_now[0]["event"], "updated");
+
+    // Nothing changed since
+    seen.lock().unwrap().clear();
+    daemon.check_for_new_notifications().await.unwrap();
+    assert!(seen.lock().unwrap().is_empty());
+
+    // Records updated together are all read again, in order
+    let first = create_test_plist_data("Downloading", "100%", "com.example.testapp", 1010.0);
+    let second = create_test_plist_data("Other", "Changed", "com.example.testapp", 1010.0);
+    db.connect().await.unwrap()
+        .call(move |conn| {
+            conn.execute("UPDATE record SET data = ?, request_last_date = 1010 WHERE ROWID = 1", [first])?;
+            conn.execute("UPDATE record SET data = ?, request_last_date = 1010 WHERE ROWID = 2", [second])?;
+            Ok(())
+        }).await.unwrap();
+    daemon.check_for_new_notifications().await.unwrap();
+    let ids: Vec<_> = seen.lock().unwrap().iter().map(|n| n["id"].clone()).collect();
+    assert_eq!(ids, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn test_watch_updates_notices_updates_to_the_skipped_backlog() {
+    let (temp_dir, db) = create_test_database().await;
+    let db_path = temp_dir.path().join("notifications.db").to_str().unwrap().to_string();
+    insert_notification(&db, 1, 1, "Downloading", "10%", "com.example.testapp", 1000.0).await;
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+    let mut daemon = NotificationDaemon::new(&db_path)
+        .with_watch_updates(true)
+        .on_notification(move |n| seen_clone.lock().unwrap().push(serde_json::to_value(n).unwrap()));
+    daemon.check_for_new_notifications().await.unwrap();
+    assert!(seen.lock().unwrap().is_empty());
+
+    let data = create_test_plist_data("Downloading", "55%", "com.example.testapp", 1005.0);
+    db.connect().await.unwrap()
+        .call(move |conn| {
+            conn.execute("UPDATE record SET data = ?, request_last_date = 1005 WHERE ROWID = 1", [data])?;
+            Ok(())
+        }).await.unwrap();
+
+    daemon.check_for_new_notifications().await.unwrap();
+    let seen_now = seen.lock().unwrap().clone();
+    assert_eq!(seen_now.len(), 1);
+    assert_eq!(seen_now[0]["body"], "55%");
+    assert_eq!(seen_now[0]["event"], "updated");
 }
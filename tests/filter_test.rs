@@ -0,0 +1,52 @@
+//! Tests for filter expressions.
+
+use blurt::database::Notification;
+use blurt::filter::Filter;
+
+fn notification(bundle_id: &str, title: &str, body: &str) -> Notification {
+    Notification {
+        bundle_id: Some(bundle_id.to_string()),
+        title: title.to_string(),
+        body: body.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_filter_matches() {
+    let filter: Filter = r#"bundle_id == "com.apple.iCal" && (body ~ "(?i)meeting" || title != "Reminder")"#.parse().unwrap();
+
+    assert!(filter.matches(&notification("com.apple.iCal", "Reminder", "Team meeting at 10")));
+    assert!(filter.matches(&notification("com.apple.iCal", "Standup", "In 5 minutes")));
+    assert!(!filter.matches(&notification("com.apple.iCal", "Reminder", "Dentist")));
+    assert!(!filter.matches(&notification("com.apple.MobileSMS", "Reminder", "Meeting moved")));
+
+    // && binds tighter than || and missing fields never match
+    let filter: Filter = r#"app_name == "Calendar" || title == "Build failed" && subtitle != "main""#.parse().unwrap();
+    assert!(filter.matches(&notification("com.example.ci", "Build failed", "")));
+    assert!(!filter.matches(&notification("com.example.ci", "Build passed", "")));
+
+    let filter: Filter = r#"title == "Say \"hi\"""#.parse().unwrap();
+    assert!(filter.matches(&notification("com.example.app", "Say \"hi\"", "")));
+}
+
+#[test]
+fn test_filter_parse_errors_point_at_the_token() {
+    let error = r#"body == "a" && sender == "b""#.parse::<Filter>().unwrap_err();
+    assert!(error.starts_with("unknown field 'sender'"), "{}", error);
+    assert!(error.contains("at column 16"), "{}", error);
+    assert!(error.ends_with("\n                 ^"), "{}", error);
+
+    let error = r#"(title == "a""#.parse::<Filter>().unwrap_err();
+    assert!(error.starts_with("unexpected end of expression, expected ')'"), "{}", error);
+
+    let error = r#"title = "a""#.parse::<Filter>().unwrap_err();
+    assert!(error.contains("expected '=='"), "{}", error);
+
+    let error = r#"body ~ "(unclosed""#.parse::<Filter>().unwrap_err();
+    assert!(error.starts_with("invalid regex"), "{}", error);
+    assert!(error.contains("at column 8"), "{}", error);
+
+    let error = r#"title == "a" title"#.parse::<Filter>().unwrap_err();
+    assert!(error.starts_with("unexpected 'title', expected '&&' or '||'"), "{}", error);
+}
@@ -0,0 +1,42 @@
+//! Tests for per-app rate limiting.
+
+use blurt::rate_limit::{Rate, RateLimiter};
+use std::time::Duration;
+
+#[test]
+fn test_parse_rate() {
+    assert_eq!("10/min".parse::<Rate>().unwrap(), Rate::new(10, Duration::from_secs(60)));
+    assert_eq!("3/hour".parse::<Rate>().unwrap(), Rate::new(3, Duration::from_secs(3600)));
+    assert_eq!("1/s".parse::<Rate>().unwrap(), Rate::new(1, Duration::from_secs(1)));
+
+    assert!("10".parse::<Rate>().unwrap_err().contains("expected COUNT/UNIT"));
+    assert!("0/min".parse::<Rate>().unwrap_err().contains("positive number"));
+    assert!("10/week".parse::<Rate>().unwrap_err().contains("Invalid unit"));
+}
+
+#[test]
+fn test_rate_limiter_buckets_per_app() {
+    let mut limiter = RateLimiter::new(Rate::new(2, Duration::from_secs(3600)));
+
+    assert!(limiter.allow(Some("com.example.noisy")));
+    assert!(limiter.allow(Some("com.example.noisy")));
+    assert!(!limiter.allow(Some("com.example.noisy")));
+
+    // Other apps have their own limit
+    assert!(limiter.allow(Some("com.example.quiet")));
+
+    // Notifications without an app share one
+    assert!(limiter.allow(None));
+    assert!(limiter.allow(None));
+    assert!(!limiter.allow(None));
+}
+
+#[test]
+fn test_rate_limiter_refills_over_the_period() {
+    let mut limiter = RateLimiter::new(Rate::new(1, Duration::from_millis(50)));
+
+    assert!(limiter.allow(Some("com.example.noisy")));
+    assert!(!limiter.allow(Some("com.example.noisy")));
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(limiter.allow(Some("com.example.noisy")));
+}
@@ -0,0 +1,46 @@
+//! Tests for the history of recent notifications.
+
+use blurt::database::Notification;
+use blurt::recent::RecentNotifications;
+
+fn notification(id: i64) -> Notification {
+    Notification {
+        id,
+        title: format!("Notification {}", id),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_recent_evicts_oldest_when_full() {
+    let recent = RecentNotifications::new(3);
+    assert!(recent.snapshot().is_empty());
+
+    for id in 1..=5 {
+        recent.push(&notification(id));
+    }
+
+    let ids: Vec<i64> = recent.snapshot().iter().map(|n| n.id).collect();
+    assert_eq!(ids, vec![3, 4, 5]);
+}
+
+#[test]
+fn test_recent_with_zero_capacity_keeps_nothing() {
+    let recent = RecentNotifications::new(0);
+    recent.push(&notification(1));
+    assert!(recent.snapshot().is_empty());
+}
+
+#[test]
+fn test_recent_ids_keep_counting_past_evictions() {
+    let recent = RecentNotifications::new(2);
+    let ids: Vec<u64> = (1..=4).map(|id| recent.push(&notification(id))).collect();
+    assert_eq!(ids, vec![1, 2, 3, 4]);
+    assert_eq!(recent.last_id(), 4);
+
+    let since: Vec<(u64, i64)> = recent.since(2).iter().map(|(id, n)| (*id, n.id)).collect();
+    assert_eq!(since, vec![(3, 3), (4, 4)]);
+    // Only what's still kept is returned for an id that was evicted
+    assert_eq!(recent.since(0).len(), 2);
+    assert!(recent.since(4).is_empty());
+}
@@ -0,0 +1,65 @@
+//! Tests for quiet hours and poll interval scheduling.
+
+use blurt::schedule::QuietHours;
+use chrono::NaiveTime;
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+#[test]
+fn test_quiet_hours_within_a_day() {
+    let quiet: QuietHours = "12:00-13:30".parse().unwrap();
+    assert!(quiet.contains(time(12, 0)));
+    assert!(quiet.contains(time(13, 29)));
+    assert!(!quiet.contains(time(13, 30)));
+    assert!(!quiet.contains(time(11, 59)));
+}
+
+#[test]
+fn test_quiet_hours_crossing_midnight() {
+    let quiet: QuietHours = "22:00-07:00".parse().unwrap();
+    assert!(quiet.contains(time(22, 0)));
+    assert!(quiet.contains(time(23, 59)));
+    assert!(quiet.contains(time(0, 0)));
+    assert!(quiet.contains(time(6, 59)));
+    assert!(!quiet.contains(time(7, 0)));
+    assert!(!quiet.contains(time(12, 0)));
+}
+
+#[test]
+fn test_quiet_hours_rejects_invalid_input() {
+    assert!("22:00".parse::<QuietHours>().is_err());
+    assert!("25:00-07:00".parse::<QuietHours>().is_err());
+    assert!("10pm-7am".parse::<QuietHours>().is_err());
+}
+
+#[test]
+fn test_adaptive_poll_interval() {
+    use blurt::interval::PollInterval;
+    use std::time::Duration;
+
+    let mut interval = PollInterval::adaptive(Duration::from_secs(1), Duration::from_secs(4), 2.0);
+    assert_eq!(interval.current(), Duration::from_secs(1));
+
+    // Grows after a few idle checks, capped at the max
+    for _ in 0..3 {
+        interval.record_check(false);
+    }
+    assert_eq!(interval.current(), Duration::from_secs(2));
+    for _ in 0..6 {
+        interval.record_check(false);
+    }
+    assert_eq!(interval.current(), Duration::from_secs(4));
+
+    // Activity drops straight back to the min
+    interval.record_check(true);
+    assert_eq!(interval.current(), Duration::from_secs(1));
+
+    // A fixed interval never changes
+    let mut fixed = PollInterval::fixed(Duration::from_secs(5));
+    for _ in 0..10 {
+        fixed.record_check(false);
+    }
+    assert_eq!(fixed.current(), Duration::from_secs(5));
+}
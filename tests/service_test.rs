@@ -0,0 +1,39 @@
+//! Tests for the LaunchAgent written by install-service.
+
+use std::path::Path;
+use blurt::service::{launch_agent, launch_agent_path, DEFAULT_LABEL};
+use plist::Value;
+
+#[test]
+fn test_launch_agent_bakes_in_daemon_args() {
+    let args = vec!["--interval".to_string(), "5".to_string(), "--webhook".to_string(), "https://example.com/hook".to_string()];
+    let agent = launch_agent(DEFAULT_LABEL, Path::new("/usr/local/bin/blurt"), &args, Path::new("/Users/me/Library/Logs/blurt"));
+
+    // Round trip through XML like launchd reads it
+    let mut xml = Vec::new();
+    agent.to_writer_xml(&mut xml).unwrap();
+    let agent = Value::from_reader_xml(xml.as_slice()).unwrap();
+    let agent = agent.as_dictionary().unwrap();
+
+    assert_eq!(agent.get("Label").and_then(Value::as_string), Some(DEFAULT_LABEL));
+    let program_arguments: Vec<&str> = agent.get("ProgramArguments")
+        .and_then(Value::as_array)
+        .unwrap()
+        .iter()
+        .map(|arg| arg.as_string().unwrap())
+        .collect();
+    assert_eq!(program_arguments, vec!["/usr/local/bin/blurt", "--interval", "5", "--webhook", "https://example.com/hook"]);
+    assert_eq!(agent.get("RunAtLoad").and_then(Value::as_boolean), Some(true));
+    assert_eq!(
+        agent.get("StandardErrorPath").and_then(Value::as_string),
+        Some("/Users/me/Library/Logs/blurt/blurt.err.log")
+    );
+}
+
+#[test]
+fn test_launch_agent_path_is_named_after_label() {
+    assert_eq!(
+        launch_agent_path(Path::new("/Users/me"), "com.example.blurt"),
+        Path::new("/Users/me/Library/LaunchAgents/com.example.blurt.plist")
+    );
+}
@@ -0,0 +1,24 @@
+//! Tests for showing dates in a timezone.
+
+use blurt::timezone::DisplayTimezone;
+use chrono::{TimeZone, Utc};
+
+#[test]
+fn test_format_in_timezone() {
+    let date = Utc.with_ymd_and_hms(2024, 7, 1, 12, 30, 0).unwrap();
+
+    assert_eq!(DisplayTimezone::default().format(date), "2024-07-01 12:30:00 UTC");
+    let paris: DisplayTimezone = "Europe/Paris".parse().unwrap();
+    assert_eq!(paris.format(date), "2024-07-01 14:30:00 CEST");
+    let new_york: DisplayTimezone = "America/New_York".parse().unwrap();
+    assert_eq!(new_york.format(Utc.with_ymd_and_hms(2024, 1, 1, 3, 0, 0).unwrap()), "2023-12-31 22:00:00 EST");
+}
+
+#[test]
+fn test_parse_timezone() {
+    assert_eq!("UTC".parse::<DisplayTimezone>().unwrap(), DisplayTimezone::Utc);
+    assert_eq!("local".parse::<DisplayTimezone>().unwrap(), DisplayTimezone::Local);
+
+    let err = "Mars/Olympus_Mons".parse::<DisplayTimezone>().unwrap_err();
+    assert!(err.contains("Unknown timezone 'Mars/Olympus_Mons'"));
+}